@@ -0,0 +1,223 @@
+use errors::*;
+use helpers::overwrites;
+use staging;
+use std::collections::BTreeMap;
+use std::fs::{self, Permissions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+type Modifier<'a> = Box<dyn FnOnce(String) -> Result<Option<String>> + 'a>;
+
+enum Change<'a> {
+    Create(PathBuf, String),
+    Modify(PathBuf, Modifier<'a>),
+    Delete(PathBuf),
+    Rename(PathBuf, PathBuf),
+}
+
+/// The content of a file, and its permissions when it exists on disk.
+#[derive(Clone)]
+struct Entry {
+    content: Vec<u8>,
+    permissions: Option<Permissions>,
+}
+
+/// Changes to several files that are applied together: either all of them are written, or the
+/// files are left as they were.
+///
+/// ```no_run
+/// # use cargo_gen_helpers::ChangeSet;
+/// let mut changes = ChangeSet::new();
+/// changes
+///     .create("src/app.rs", "pub fn app() {}")
+///     .modify("src/lib.rs", |contents| Ok(Some(format!("pub mod app;\n{}", contents))));
+/// changes.apply().unwrap();
+/// ```
+#[derive(Default)]
+pub struct ChangeSet<'a> {
+    changes: Vec<Change<'a>>,
+}
+
+impl<'a> ChangeSet<'a> {
+    pub fn new() -> ChangeSet<'a> {
+        ChangeSet::default()
+    }
+
+    /// Creates the file with the directories it needs. As with `create_file`, the conflict policy
+    /// says what happens to a file that exists.
+    pub fn create<P: AsRef<Path>>(&mut self, path: P, content: &str) -> &mut ChangeSet<'a> {
+        let change = Change::Create(path.as_ref().to_path_buf(), content.to_owned());
+        self.changes.push(change);
+        self
+    }
+
+    /// Modifies a file that exists, or that a change before creates. As with `modify_file`, the
+    /// modifier returns `None` to leave the file unchanged.
+    pub fn modify<P, F>(&mut self, path: P, modifier: F) -> &mut ChangeSet<'a>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(String) -> Result<Option<String>> + 'a,
+    {
+        let change = Change::Modify(path.as_ref().to_path_buf(), Box::new(modifier));
+        self.changes.push(change);
+        self
+    }
+
+    pub fn delete<P: AsRef<Path>>(&mut self, path: P) -> &mut ChangeSet<'a> {
+        self.changes.push(Change::Delete(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Renames a file, the new path must not be taken.
+    pub fn rename<P, Q>(&mut self, from: P, to: Q) -> &mut ChangeSet<'a>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let change = Change::Rename(from.as_ref().to_path_buf(), to.as_ref().to_path_buf());
+        self.changes.push(change);
+        self
+    }
+
+    /// Checks the changes in order, then writes the files they touch. Nothing is written if a
+    /// check fails, and the files written are restored if a write fails.
+    pub fn apply(self) -> Result<()> {
+        let mut originals = BTreeMap::new();
+        let mut planned = BTreeMap::new();
+        for change in self.changes {
+            plan(change, &mut originals, &mut planned)?;
+        }
+
+        let mut created_dirs = vec![];
+        let mut written = vec![];
+        for (path, entry) in &planned {
+            if let Err(e) = write(path, entry, &mut created_dirs) {
+                let restored = restore(&written, &originals, &created_dirs);
+                return Err(e).chain_err(|| match restored {
+                    Ok(()) => format!("{} could not be written, no file changed", path.display()),
+                    Err(restore_error) => format!(
+                        "{} could not be written, and restoring the files written before failed: \
+                         {}",
+                        path.display(),
+                        restore_error
+                    ),
+                });
+            }
+            written.push(path.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Applies `change` to the planned files. `originals` keeps what each file touched was on disk.
+fn plan(
+    change: Change,
+    originals: &mut BTreeMap<PathBuf, Option<Entry>>,
+    planned: &mut BTreeMap<PathBuf, Option<Entry>>,
+) -> Result<()> {
+    let mut current = |path: &Path| -> Result<Option<Entry>> {
+        if !planned.contains_key(path) {
+            let entry = read_entry(path)?;
+            originals.insert(path.to_path_buf(), entry.clone());
+            planned.insert(path.to_path_buf(), entry);
+        }
+        Ok(planned[path].clone())
+    };
+    match change {
+        Change::Create(path, content) => {
+            let entry = current(&path)?;
+            if entry.is_some() && !overwrites(&path)? {
+                return Ok(());
+            }
+            let permissions = entry.and_then(|entry| entry.permissions);
+            let content = content.into_bytes();
+            planned.insert(path, Some(Entry { content, permissions }));
+        }
+        Change::Modify(path, modifier) => {
+            let entry = match current(&path)? {
+                Some(entry) => entry,
+                None => bail!("{} could not be modified, it does not exist", path.display()),
+            };
+            let content = String::from_utf8(entry.content)
+                .chain_err(|| format!("{} is not UTF-8", path.display()))?;
+            if let Some(modified_content) = modifier(content)? {
+                let content = modified_content.into_bytes();
+                let permissions = entry.permissions;
+                planned.insert(path, Some(Entry { content, permissions }));
+            }
+        }
+        Change::Delete(path) => {
+            if current(&path)?.is_none() {
+                bail!("{} could not be deleted, it does not exist", path.display());
+            }
+            planned.insert(path, None);
+        }
+        Change::Rename(from, to) => {
+            let entry = match current(&from)? {
+                Some(entry) => entry,
+                None => bail!("{} could not be renamed, it does not exist", from.display()),
+            };
+            if current(&to)?.is_some() {
+                bail!(
+                    "{} could not be renamed to {}, it exists",
+                    from.display(),
+                    to.display()
+                );
+            }
+            planned.insert(from, None);
+            planned.insert(to, Some(entry));
+        }
+    }
+    Ok(())
+}
+
+fn read_entry(path: &Path) -> Result<Option<Entry>> {
+    let content =
+        staging::read(path).chain_err(|| format!("{} could not be read", path.display()))?;
+    Ok(content.map(|content| Entry {
+        content,
+        permissions: staging::permissions(path),
+    }))
+}
+
+/// Writes or removes the file at `path`. The directories it creates are added to `created_dirs`,
+/// the deepest last.
+fn write(path: &Path, entry: &Option<Entry>, created_dirs: &mut Vec<PathBuf>) -> io::Result<()> {
+    match *entry {
+        Some(ref entry) => {
+            if let (Some(dir), false) = (path.parent(), staging::is_dry_run()) {
+                let mut missing = dir
+                    .ancestors()
+                    .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+                    .map(|dir| dir.to_path_buf())
+                    .collect::<Vec<_>>();
+                missing.reverse();
+                created_dirs.extend(missing);
+            }
+            staging::write(path, &entry.content)?;
+            match entry.permissions {
+                Some(ref permissions) => staging::set_permissions(path, permissions.clone()),
+                None => Ok(()),
+            }
+        }
+        None => staging::remove(path),
+    }
+}
+
+/// Puts the `written` files back as they were, and removes the directories created for them.
+fn restore(
+    written: &[PathBuf],
+    originals: &BTreeMap<PathBuf, Option<Entry>>,
+    created_dirs: &[PathBuf],
+) -> io::Result<()> {
+    let mut result = Ok(());
+    for path in written {
+        let restored = write(path, &originals[path], &mut vec![]);
+        result = result.and(restored);
+    }
+    for dir in created_dirs.iter().rev() {
+        // The directories hold nothing else unless someone wrote there meanwhile, they stay then.
+        let _ = fs::remove_dir(dir);
+    }
+    result
+}
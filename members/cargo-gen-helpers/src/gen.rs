@@ -1,7 +1,8 @@
 use askama::Template;
+use change_set::ChangeSet;
 use errors::*;
 use gen_trait::CargoGenerator;
-use helpers::{create_file, modify_file};
+use yaml::clap_yaml;
 use clap::{App, SubCommand};
 use std::ffi::OsString;
 use std::path::{Path, PathBuf};
@@ -30,8 +31,9 @@ where
 {
     fn from(clargs: I) -> CargoGeneratorGenerator {
         let yml = load_yaml!("../cargo_generators.yaml");
+        let yml = clap_yaml(&yml[0]);
         let args = App::new("")
-            .subcommand(SubCommand::with_name("gen").subcommand(SubCommand::from_yaml(&yml[0])))
+            .subcommand(SubCommand::with_name("gen").subcommand(SubCommand::from_yaml(&yml)))
             .get_matches_from(clargs);
         let gen_args = args.subcommand_matches("gen")
             .expect("'gen' subcommand expected but not provided");
@@ -47,53 +49,55 @@ where
 
 impl CargoGenerator for CargoGeneratorGenerator {
     fn gen(&self) -> Result<()> {
+        let mut changes = ChangeSet::new();
+
         // cargo generators module
         // TODO: modify existing
-        create_file(
+        changes.create(
             self.root.join("src/cargo_generators/mod.rs"),
             "pub mod app;",
-        )?;
+        );
 
         // cargo generator
         let gen_file_content = GenFileTemplate {}.render()?;
         let path = self.root
             .join(format!("src/cargo_generators/{}.rs", self.short_name));
-        create_file(path, &gen_file_content)?;
+        changes.create(path, &gen_file_content);
 
         // expose cargo generators in lib.rs
-        modify_file(self.root.join("src/lib.rs"), |mut contents| {
+        changes.modify(self.root.join("src/lib.rs"), |mut contents| {
             contents.insert_str(
                 0,
                 "#[macro_use]\nextern crate clap;\n\npub mod cargo_generators;\n",
             );
             Ok(Some(contents))
-        })?;
+        });
 
         // cargo generators test loader module
-        create_file(
+        changes.create(
             self.root.join("tests/cargo_gen.rs"),
             "mod cargo_generators;",
-        )?;
+        );
 
         // cargo gen test module
         // TODO: modify existing
-        create_file(self.root.join("tests/cargo_generators/mod.rs"), "mod app;")?;
+        changes.create(self.root.join("tests/cargo_generators/mod.rs"), "mod app;");
 
         // cargo gen test
         let test_file_content = TestFileTemplate {}.render()?;
         let path = self.root
             .join(format!("tests/cargo_generators/{}.rs", self.short_name));
-        create_file(path, &test_file_content)?;
+        changes.create(path, &test_file_content);
 
         // Create a clap command line specifications YAML file
         // TODO: modify existing
         let clap_file_content = ClapYamlFileTemplate {}.render()?;
         let path = self.root.join("cargo_generators.yaml");
-        create_file(path, &clap_file_content)?;
+        changes.create(path, &clap_file_content);
 
         // Cargo.toml
         // TODO: move to helper
-        modify_file(self.root.join("Cargo.toml"), |mut contents| {
+        changes.modify(self.root.join("Cargo.toml"), |mut contents| {
             // TODO: use a TOML parser that preserves order, whitespace, etc. At the moment the
             // toml crate does not.
 
@@ -110,7 +114,10 @@ impl CargoGenerator for CargoGeneratorGenerator {
             contents = contents.replace(deps_str, &gen_helper_dep);
 
             Ok(Some(contents))
-        })
+        });
+
+        // Nothing is generated unless all of it can be.
+        changes.apply()
     }
 }
 
@@ -1,20 +1,26 @@
 use errors::*;
-use std::fs::{create_dir_all, File, OpenOptions};
-use std::io::{Read, Write};
-use std::path::Path;
+use staging;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 
+/// Creates the file with the directories it needs. A file that exists already is overwritten,
+/// left as it is or reported as an error, as the conflict policy says.
 pub fn create_file<P>(path: P, content: &str) -> Result<()>
 where
     P: AsRef<Path>,
 {
     let the_path = path.as_ref();
-    if let Some(dir) = the_path.parent() {
-        create_dir_all(dir)?;
+    let exists = staging::read(the_path)
+        .chain_err(|| format!("{} could not be read", the_path.display()))?
+        .is_some();
+    if exists && !overwrites(the_path)? {
+        return Ok(());
     }
-    File::create(&path)
-        .chain_err(|| format!("{} could not be created", the_path.display()))?
-        .write_all(content.as_bytes())
-        .chain_err(|| format!("Writing to {} failed", the_path.display()))
+    staging::write(the_path, content.as_bytes())
+        .chain_err(|| format!("{} could not be created", the_path.display()))
 }
 
 pub fn modify_file<P, F>(path: P, modifier: F) -> Result<()>
@@ -22,16 +28,112 @@ where
     F: FnOnce(String) -> Result<Option<String>>,
     P: AsRef<Path>,
 {
-    let mut content = String::new();
-    File::open(&path)?.read_to_string(&mut content)?;
+    let the_path = path.as_ref();
+    let content = match staging::read(the_path)? {
+        Some(content) => String::from_utf8(content)
+            .chain_err(|| format!("{} is not UTF-8", the_path.display()))?,
+        None => bail!("{} could not be modified, it does not exist", the_path.display()),
+    };
     if let Some(modified_content) = modifier(content)? {
-        OpenOptions::new()
-            .write(true)
-            .open(&path)
-            .chain_err(|| format!("{} could not be written", path.as_ref().display()))?
-            .write_all(modified_content.as_bytes())
-            .chain_err(|| format!("Writing to {} failed", path.as_ref().display()))
+        staging::write(the_path, modified_content.as_bytes())
+            .chain_err(|| format!("{} could not be written", the_path.display()))
     } else {
         Ok(())
     }
 }
+
+/// Writes `content` to a temporary file next to `path` and renames it over `path`, so that `path`
+/// has either its old or its new content if writing fails half way. An existing file keeps its
+/// permissions. A symbolic link is written through, to the file it leads to.
+pub(crate) fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let path = &resolve_links(path)?;
+    let (temp_path, mut temp_file) = create_temp_file(path)?;
+    let written = temp_file
+        .write_all(content)
+        .and_then(|_| temp_file.sync_all())
+        .and_then(|_| match fs::metadata(path) {
+            Ok(metadata) => fs::set_permissions(&temp_path, metadata.permissions()),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    written
+}
+
+/// The file that `path` leads to through its symbolic links, which may not exist yet.
+fn resolve_links(path: &Path) -> io::Result<PathBuf> {
+    let mut resolved = path.to_path_buf();
+    // As many links as Linux follows before giving up.
+    for _ in 0..40 {
+        match fs::symlink_metadata(&resolved) {
+            Ok(ref metadata) if metadata.file_type().is_symlink() => {
+                let target = fs::read_link(&resolved)?;
+                resolved = match resolved.parent() {
+                    Some(parent) => parent.join(target),
+                    None => target,
+                };
+            }
+            Ok(_) => return Ok(resolved),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(resolved),
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} has too many levels of symbolic links", path.display()),
+    ))
+}
+
+/// A new hidden file in the directory of `path`, named after it.
+fn create_temp_file(path: &Path) -> io::Result<(PathBuf, File)> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "The path has no file name"))?;
+    let mut attempt = 0;
+    loop {
+        let temp_path = path.with_file_name(format!(
+            ".{}.{}-{}.tmp",
+            file_name.to_string_lossy(),
+            process::id(),
+            attempt
+        ));
+        match OpenOptions::new().write(true).create_new(true).open(&temp_path) {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => {
+                attempt += 1
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// A template variable of the project, from the `[variables]` of its `.cargo-gen.toml` or of the
+/// user config, or `author` and `author-email` from its `[user]`. cargo gen passes them to the
+/// generator as `CARGO_GEN_VAR_<NAME>` environment variables.
+pub fn template_variable(name: &str) -> Option<String> {
+    env::var(format!("CARGO_GEN_VAR_{}", name.to_uppercase().replace('-', "_"))).ok()
+}
+
+/// What to do with the files that exist already, `overwrite`, `skip` or `fail`, if the user or
+/// the project config says so.
+pub fn conflict_policy() -> Option<String> {
+    env::var("CARGO_GEN_CONFLICT").ok()
+}
+
+/// Whether to overwrite `path`, which exists, as the conflict policy says. Fails if the policy is
+/// `fail`.
+pub(crate) fn overwrites(path: &Path) -> Result<bool> {
+    match conflict_policy().as_deref() {
+        None | Some("overwrite") => Ok(true),
+        Some("skip") => Ok(false),
+        Some("fail") => bail!("{} could not be created, it exists", path.display()),
+        Some(policy) => bail!(
+            "{} could not be created, the conflict policy {} is none of overwrite, skip or fail",
+            path.display(),
+            policy
+        ),
+    }
+}
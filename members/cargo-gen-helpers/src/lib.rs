@@ -4,6 +4,7 @@ extern crate askama;
 extern crate clap;
 #[macro_use]
 extern crate error_chain;
+extern crate yaml_rust;
 
 // TODO: move this into a standalone crate. We don't want to compile cargo and tempdir when they
 // are not used.
@@ -20,10 +21,16 @@ pub mod errors {
     }
 }
 
+mod change_set;
 mod gen_trait;
 mod helpers;
+mod staging;
+mod yaml;
 pub mod gen;
 pub mod test_helpers;
 
+pub use change_set::ChangeSet;
 pub use gen_trait::CargoGenerator;
-pub use helpers::{create_file, modify_file};
+pub use helpers::{conflict_policy, create_file, modify_file, template_variable};
+pub use staging::is_dry_run;
+pub use yaml::clap_yaml;
@@ -0,0 +1,141 @@
+//! The file system as the helpers see it. On a dry run, `cargo gen` sets `CARGO_GEN_DRY_RUN` to a
+//! staging directory: the files written then go to `files/` in it, under their absolute path, and
+//! the files removed are marked in `removed/`. The real files are only read.
+//!
+//! Otherwise `cargo gen` may set `CARGO_GEN_BACKUP` to a directory the files are copied to, under
+//! their absolute path, before they are first written or removed, so that `cargo gen undo` can put
+//! them back.
+use helpers::write_atomically;
+use std::env;
+use std::fs::{self, create_dir_all, File, Permissions};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// The staging directory of a dry run.
+const DRY_RUN_ENV: &str = "CARGO_GEN_DRY_RUN";
+/// Where the written files are staged, relative to the staging directory.
+const STAGED_FILES_DIR: &str = "files";
+/// Where the removed files are marked, relative to the staging directory.
+const REMOVED_FILES_DIR: &str = "removed";
+/// The backup directory of a run that can be undone.
+const BACKUP_ENV: &str = "CARGO_GEN_BACKUP";
+
+/// Whether the generator runs for `cargo gen run --dry-run`, the files are left alone then.
+pub fn is_dry_run() -> bool {
+    staging_dir().is_some()
+}
+
+fn staging_dir() -> Option<PathBuf> {
+    env::var_os(DRY_RUN_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Copies the file at `path` to the backup directory, if there is one and the file is not backed
+/// up yet.
+fn back_up(path: &Path) -> io::Result<()> {
+    let dir = match env::var_os(BACKUP_ENV).filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => return Ok(()),
+    };
+    let backup = staged_path(&dir, path)?;
+    if backup.exists() || !path.is_file() {
+        return Ok(());
+    }
+    if let Some(dir) = backup.parent() {
+        create_dir_all(dir)?;
+    }
+    fs::copy(path, backup).map(|_| ())
+}
+
+/// `path` in `dir`: the staging directory, `files/` and `removed/` keep the absolute paths of the
+/// files without their root.
+fn staged_path(dir: &Path, path: &Path) -> io::Result<PathBuf> {
+    let mut staged = dir.to_path_buf();
+    for component in env::current_dir()?.join(path).components() {
+        match component {
+            Component::Normal(name) => staged.push(name),
+            Component::ParentDir if staged != dir => {
+                staged.pop();
+            }
+            _ => {}
+        }
+    }
+    Ok(staged)
+}
+
+/// The content of the file at `path`, `None` if there is none.
+pub(crate) fn read(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    if let Some(dir) = staging_dir() {
+        if staged_path(&dir.join(REMOVED_FILES_DIR), path)?.exists() {
+            return Ok(None);
+        }
+        let staged = staged_path(&dir.join(STAGED_FILES_DIR), path)?;
+        if staged.is_file() {
+            return fs::read(staged).map(Some);
+        }
+    }
+    // A path under a file does not exist either, writing it reports the error.
+    if !path.exists() {
+        return Ok(None);
+    }
+    fs::read(path).map(Some)
+}
+
+/// Writes the file at `path` atomically, with the directories it needs.
+pub(crate) fn write(path: &Path, content: &[u8]) -> io::Result<()> {
+    let target = match staging_dir() {
+        Some(dir) => {
+            let removed = staged_path(&dir.join(REMOVED_FILES_DIR), path)?;
+            if removed.exists() {
+                fs::remove_file(removed)?;
+            }
+            staged_path(&dir.join(STAGED_FILES_DIR), path)?
+        }
+        None => {
+            back_up(path)?;
+            path.to_path_buf()
+        }
+    };
+    if let Some(dir) = target.parent() {
+        create_dir_all(dir)?;
+    }
+    write_atomically(&target, content)
+}
+
+/// Removes the file at `path` if there is one.
+pub(crate) fn remove(path: &Path) -> io::Result<()> {
+    let result = match staging_dir() {
+        Some(dir) => {
+            let staged = staged_path(&dir.join(STAGED_FILES_DIR), path)?;
+            if staged.exists() {
+                fs::remove_file(staged)?;
+            }
+            let removed = staged_path(&dir.join(REMOVED_FILES_DIR), path)?;
+            if !path.is_file() {
+                return Ok(());
+            }
+            if let Some(dir) = removed.parent() {
+                create_dir_all(dir)?;
+            }
+            File::create(removed).map(|_| ())
+        }
+        None => back_up(path).and_then(|_| fs::remove_file(path)),
+    };
+    match result {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// The permissions of the file at `path`, `None` if it is only staged.
+pub(crate) fn permissions(path: &Path) -> Option<Permissions> {
+    fs::metadata(path).ok().map(|metadata| metadata.permissions())
+}
+
+pub(crate) fn set_permissions(path: &Path, permissions: Permissions) -> io::Result<()> {
+    if is_dry_run() {
+        return Ok(());
+    }
+    fs::set_permissions(path, permissions)
+}
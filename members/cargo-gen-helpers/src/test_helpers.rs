@@ -5,7 +5,7 @@ use errors::*;
 use helpers::create_file;
 use std::fs::File;
 use std::io::Read;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use tempdir::TempDir;
 
 pub fn read_file_to_string(path: PathBuf) -> Result<String> {
@@ -14,6 +14,23 @@ pub fn read_file_to_string(path: PathBuf) -> Result<String> {
     Ok(content)
 }
 
+/// The content of the file at `path`, panics if it cannot be read.
+pub fn read_content(path: &Path) -> String {
+    read_file_to_string(path.to_path_buf()).unwrap()
+}
+
+/// Where `dir` keeps the file at the absolute `path`: the staging directory of a dry run and the
+/// backup directory keep the files under their absolute path without its root.
+pub fn staged_path(dir: &Path, path: &Path) -> PathBuf {
+    let mut staged = dir.to_path_buf();
+    for component in path.components() {
+        if let Component::Normal(name) = component {
+            staged.push(name);
+        }
+    }
+    staged
+}
+
 pub fn create_empty_crate(name: &str) -> Result<TempDir> {
     let tempdir = TempDir::new(name)?;
     {
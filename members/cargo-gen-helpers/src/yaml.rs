@@ -0,0 +1,48 @@
+use yaml_rust::Yaml;
+
+/// The YAML of a generator without the settings of its arguments that only `cargo gen` reads, as
+/// clap panics on the settings it does not know.
+///
+/// ```ignore
+/// let yml = load_yaml!("../cargo_generators.yaml");
+/// let yml = clap_yaml(&yml[0]);
+/// let app = App::new("").subcommand(SubCommand::from_yaml(&yml));
+/// ```
+pub fn clap_yaml(generator: &Yaml) -> Yaml {
+    let mut generator = generator.clone();
+    if let Yaml::Hash(ref mut generator) = generator {
+        if let Some(&mut Yaml::Array(ref mut args)) = generator.get_mut(&key("args")) {
+            for arg in args {
+                if let Yaml::Hash(ref mut arg) = *arg {
+                    for (_, settings) in arg.iter_mut() {
+                        if let Yaml::Hash(ref mut settings) = *settings {
+                            settings.remove(&key("prompt"));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    generator
+}
+
+fn key(name: &str) -> Yaml {
+    Yaml::String(name.to_owned())
+}
+
+#[cfg(test)]
+mod yaml_test {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    #[test]
+    fn it_removes_the_prompts_of_the_args() {
+        let yaml = YamlLoader::load_from_str(
+            "name: a\nargs:\n  - NAME:\n      help: The name\n      prompt: Name?\n  - force:",
+        ).unwrap();
+        let expected =
+            YamlLoader::load_from_str("name: a\nargs:\n  - NAME:\n      help: The name\n  - force:")
+                .unwrap();
+        assert_eq!(expected[0], clap_yaml(&yaml[0]));
+    }
+}
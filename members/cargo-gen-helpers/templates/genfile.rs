@@ -1,7 +1,8 @@
 extern crate cargo_gen_helpers;
 
 use self::cargo_gen_helpers::errors::Result as CGHResult;
-use self::cargo_gen_helpers::{create_file, modify_file, CargoGenerator};
+use self::cargo_gen_helpers::{clap_yaml, create_file, modify_file, template_variable,
+                              CargoGenerator};
 use clap::{App, SubCommand};
 use std::ffi::OsString;
 use std::path::{Path, PathBuf};
@@ -17,8 +18,9 @@ where
 {
     fn from(clargs: I) -> AppGenerator {
         let yml = load_yaml!("../../cargo_generators.yaml");
+        let yml = clap_yaml(&yml[0]);
         let args = App::new("")
-            .subcommand(SubCommand::with_name("gen").subcommand(SubCommand::from_yaml(&yml[0])))
+            .subcommand(SubCommand::with_name("gen").subcommand(SubCommand::from_yaml(&yml)))
             .get_matches_from(clargs);
         let gen_args = args.subcommand_matches("gen")
             .expect("'gen' subcommand expected but not provided");
@@ -42,14 +44,20 @@ impl CargoGenerator for AppGenerator {
                     .replace("assert!(2 + 2, 4)", "assert_eq!(4, add_2(2))"),
             ))
         })?;
+        // The template variables of the project, `author` among them, are set by cargo gen.
+        let header = match template_variable("author") {
+            Some(author) => format!("// Written by {}.\n\n", author),
+            None => String::new(),
+        };
         // FIXME: use the actual crate name
         create_file(
             self.root.join("tests/adds_2.rs"),
-            "extern crate gen_test;\n\n\
-             #[test]\n\
-             fn it_adds_2() {\n    \
-             assert_eq!(4, gen_test::add_2(2));\n\
-             }",
+            &(header
+                + "extern crate gen_test;\n\n\
+                   #[test]\n\
+                   fn it_adds_2() {\n    \
+                   assert_eq!(4, gen_test::add_2(2));\n\
+                   }"),
         )
     }
 }
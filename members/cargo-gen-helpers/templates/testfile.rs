@@ -5,6 +5,7 @@ use self::cargo_gen_helpers::CargoGenerator;
 use self::cargo_gen_helpers::test_helpers::{create_empty_crate, read_file_to_string,
                                             run_generated_tests};
 use self::cargo_gen_test::cargo_generators::app::AppGenerator;
+use std::env;
 use std::vec::IntoIter;
 
 fn args<'a>(suffix: &'a [&str]) -> IntoIter<&'a str> {
@@ -53,6 +54,15 @@ fn it_creates_an_integration_test() {
     );
 }
 
+#[test]
+fn it_credits_the_author_of_the_project() {
+    env::set_var("CARGO_GEN_VAR_AUTHOR", "Jane Doe");
+    let crate_dir = create_empty_crate("gen-test").unwrap();
+    run_with_args(args(&["--crate-root", crate_dir.path().to_str().unwrap()]));
+    let content = read_file_to_string(crate_dir.path().join("tests/adds_2.rs")).unwrap();
+    assert!(content.starts_with("// Written by Jane Doe.\n\nextern crate gen_test;"));
+}
+
 #[test]
 fn generated_code_passes_the_generated_tests() {
     let crate_dir = create_empty_crate("gen-test").unwrap();
@@ -7,6 +7,7 @@ mod test_create_file {
     use std::io::Read;
     use tempdir::TempDir;
     use cargo_gen_helpers::create_file;
+    use cargo_gen_helpers::test_helpers::read_content;
 
     #[test]
     fn it_creates_a_file_with_supplied_content() {
@@ -35,12 +36,47 @@ mod test_create_file {
         assert_eq!("pub fn foo() {}", content);
         println!("{:?}", tempdir);
     }
+
+    #[test]
+    fn it_replaces_a_longer_file() {
+        let tempdir = TempDir::new("cargo-gen-test").unwrap();
+        let tempfile_path = tempdir.path().join("file.rs");
+        create_file(&tempfile_path, "pub fn foo() {}\npub fn bar() {}").unwrap();
+
+        create_file(&tempfile_path, "pub fn foo() {}").unwrap();
+
+        assert_eq!("pub fn foo() {}", read_content(&tempfile_path));
+        assert_eq!(1, tempdir.path().read_dir().unwrap().count());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn it_writes_through_a_symbolic_link() {
+        use std::fs::{create_dir, read_link, symlink_metadata};
+        use std::os::unix::fs::symlink;
+        use std::path::Path;
+
+        let tempdir = TempDir::new("cargo-gen-test").unwrap();
+        let (shared, project) = (tempdir.path().join("shared"), tempdir.path().join("project"));
+        create_dir(&project).unwrap();
+        create_file(shared.join("file.rs"), "pub fn foo() {}").unwrap();
+        symlink("../shared/file.rs", project.join("file.rs")).unwrap();
+
+        create_file(project.join("file.rs"), "pub fn bar() {}").unwrap();
+
+        assert_eq!("pub fn bar() {}", read_content(&shared.join("file.rs")));
+        assert!(symlink_metadata(project.join("file.rs")).unwrap().file_type().is_symlink());
+        assert_eq!(Path::new("../shared/file.rs"), read_link(project.join("file.rs")).unwrap());
+        assert_eq!(1, project.read_dir().unwrap().count());
+        assert_eq!(1, shared.read_dir().unwrap().count());
+    }
 }
 
 mod test_modify_file {
     use tempfile::NamedTempFile;
-    use std::io::{Error, Read, Seek, SeekFrom, Write};
+    use std::io::{Error, Write};
     use cargo_gen_helpers::modify_file;
+    use cargo_gen_helpers::test_helpers::read_content;
 
     fn make_temp_file(content: &[u8]) -> Result<NamedTempFile, Error> {
         let mut tmp_file = NamedTempFile::new()?;
@@ -76,27 +112,146 @@ mod test_modify_file {
 
     #[test]
     fn it_writes_new_content_to_file() {
-        let mut tmp_file = make_temp_file(b"The content.").unwrap();
+        let tmp_file = make_temp_file(b"The content.").unwrap();
 
         modify_file(tmp_file.path(), |_| Ok(Some("New content.".to_string()))).unwrap();
 
-        tmp_file.seek(SeekFrom::Start(0)).unwrap();
-        let mut new_content = String::new();
-        tmp_file.read_to_string(&mut new_content).unwrap();
-        assert_eq!("New content.", new_content);
+        assert_eq!("New content.", read_content(tmp_file.path()));
     }
 
     #[test]
     fn it_writes_modified_content_to_file() {
-        let mut tmp_file = make_temp_file(b"The content.").unwrap();
+        let tmp_file = make_temp_file(b"The content.").unwrap();
 
         modify_file(tmp_file.path(),
                     |content| Ok(Some(content.replace("The", "New"))))
             .unwrap();
 
-        tmp_file.seek(SeekFrom::Start(0)).unwrap();
-        let mut new_content = String::new();
-        tmp_file.read_to_string(&mut new_content).unwrap();
-        assert_eq!("New content.", new_content);
+        assert_eq!("New content.", read_content(tmp_file.path()));
+    }
+
+    #[test]
+    fn it_truncates_shorter_content() {
+        let tmp_file = make_temp_file(b"The content, and more.").unwrap();
+
+        modify_file(tmp_file.path(), |content| Ok(Some(content.replace(", and more", ""))))
+            .unwrap();
+
+        assert_eq!("The content.", read_content(tmp_file.path()));
+    }
+
+    #[test]
+    fn it_empties_the_file() {
+        let tmp_file = make_temp_file(b"The content.").unwrap();
+
+        modify_file(tmp_file.path(), |_| Ok(Some(String::new()))).unwrap();
+
+        assert_eq!("", read_content(tmp_file.path()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn it_keeps_the_permissions_of_the_file() {
+        use std::fs::{metadata, set_permissions};
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp_file = make_temp_file(b"#!/bin/sh\necho content").unwrap();
+        let mut permissions = metadata(tmp_file.path()).unwrap().permissions();
+        permissions.set_mode(0o754);
+        set_permissions(tmp_file.path(), permissions).unwrap();
+
+        modify_file(tmp_file.path(), |_| Ok(Some("#!/bin/sh".to_string()))).unwrap();
+
+        assert_eq!("#!/bin/sh", read_content(tmp_file.path()));
+        let mode = metadata(tmp_file.path()).unwrap().permissions().mode();
+        assert_eq!(0o754, mode & 0o777);
+    }
+}
+
+mod test_change_set {
+    use tempdir::TempDir;
+    use cargo_gen_helpers::{create_file, ChangeSet};
+    use cargo_gen_helpers::test_helpers::read_content;
+
+    #[test]
+    fn it_applies_the_changes_in_order() {
+        let tempdir = TempDir::new("cargo-gen-test").unwrap();
+        let root = tempdir.path();
+        create_file(root.join("src/lib.rs"), "mod a;").unwrap();
+        create_file(root.join("src/old.rs"), "old").unwrap();
+        create_file(root.join("src/b.rs"), "b").unwrap();
+        // The modifiers may borrow what outlives the change set.
+        let module = String::from("app");
+
+        let mut changes = ChangeSet::new();
+        changes
+            .create(root.join("src/app/mod.rs"), "app")
+            .modify(root.join("src/app/mod.rs"), |content| Ok(Some(content + "!")))
+            .modify(root.join("src/lib.rs"), |content| {
+                Ok(Some(format!("{}\nmod {};", content, module)))
+            })
+            .delete(root.join("src/old.rs"))
+            .rename(root.join("src/b.rs"), root.join("src/c.rs"));
+        changes.apply().unwrap();
+
+        assert_eq!("app!", read_content(&root.join("src/app/mod.rs")));
+        assert_eq!("mod a;\nmod app;", read_content(&root.join("src/lib.rs")));
+        assert_eq!("b", read_content(&root.join("src/c.rs")));
+        assert!(!root.join("src/old.rs").exists());
+        assert!(!root.join("src/b.rs").exists());
+    }
+
+    #[test]
+    fn it_changes_nothing_if_a_check_fails() {
+        let tempdir = TempDir::new("cargo-gen-test").unwrap();
+        let root = tempdir.path();
+        create_file(root.join("Cargo.toml"), "[package]").unwrap();
+
+        let mut changes = ChangeSet::new();
+        changes
+            .create(root.join("src/app.rs"), "app")
+            .modify(root.join("Cargo.toml"), |content| Ok(Some(content + "\n")))
+            .modify(root.join("src/lib.rs"), |content| Ok(Some(content + "mod app;")));
+        let err = changes.apply().unwrap_err();
+
+        assert!(err.to_string().contains("does not exist"), "{}", err);
+        assert_eq!("[package]", read_content(&root.join("Cargo.toml")));
+        assert!(!root.join("src").exists());
+    }
+
+    #[test]
+    fn it_restores_the_files_if_a_write_fails() {
+        let tempdir = TempDir::new("cargo-gen-test").unwrap();
+        let root = tempdir.path();
+        create_file(root.join("Cargo.toml"), "[package]").unwrap();
+        // A file where a directory is needed.
+        create_file(root.join("tests"), "").unwrap();
+
+        let mut changes = ChangeSet::new();
+        changes
+            .modify(root.join("Cargo.toml"), |content| Ok(Some(content + "\n")))
+            .create(root.join("src/app.rs"), "app")
+            .create(root.join("tests/app.rs"), "app");
+        let err = changes.apply().unwrap_err();
+
+        assert!(err.to_string().contains("no file changed"), "{}", err);
+        assert_eq!("[package]", read_content(&root.join("Cargo.toml")));
+        assert!(!root.join("src").exists());
+    }
+}
+
+mod test_template_variable {
+    use std::env;
+    use cargo_gen_helpers::template_variable;
+
+    #[test]
+    fn it_reads_the_variable_cargo_gen_passes() {
+        env::set_var("CARGO_GEN_VAR_AUTHOR_EMAIL", "jane@example.com");
+        assert_eq!(
+            Some("jane@example.com".to_owned()),
+            template_variable("author-email")
+        );
+        env::remove_var("CARGO_GEN_VAR_AUTHOR_EMAIL");
+        assert_eq!(None, template_variable("author-email"));
     }
 }
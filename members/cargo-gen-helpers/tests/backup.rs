@@ -0,0 +1,32 @@
+extern crate cargo_gen_helpers;
+extern crate tempdir;
+
+use cargo_gen_helpers::test_helpers::{read_content, staged_path};
+use cargo_gen_helpers::{create_file, modify_file, ChangeSet};
+use std::env;
+use tempdir::TempDir;
+
+// The backup directory is set for the whole process, so this is the only test here.
+#[test]
+fn it_backs_the_files_up_before_they_are_first_written_or_removed() {
+    let project = TempDir::new("cargo-gen-test").unwrap();
+    let root = project.path();
+    create_file(root.join("src/lib.rs"), "mod a;").unwrap();
+    create_file(root.join("src/old.rs"), "old").unwrap();
+    let backup_dir = TempDir::new("cargo-gen-backup").unwrap();
+    env::set_var("CARGO_GEN_BACKUP", backup_dir.path());
+
+    create_file(root.join("src/app.rs"), "app").unwrap();
+    modify_file(root.join("src/lib.rs"), |content| Ok(Some(content + "\nmod app;"))).unwrap();
+    let mut changes = ChangeSet::new();
+    changes
+        .delete(root.join("src/old.rs"))
+        .modify(root.join("src/lib.rs"), |content| Ok(Some(content + "\nmod b;")));
+    changes.apply().unwrap();
+
+    assert_eq!("mod a;\nmod app;\nmod b;", read_content(&root.join("src/lib.rs")));
+    assert!(!root.join("src/old.rs").exists());
+    assert_eq!("mod a;", read_content(&staged_path(backup_dir.path(), &root.join("src/lib.rs"))));
+    assert_eq!("old", read_content(&staged_path(backup_dir.path(), &root.join("src/old.rs"))));
+    assert!(!staged_path(backup_dir.path(), &root.join("src/app.rs")).exists());
+}
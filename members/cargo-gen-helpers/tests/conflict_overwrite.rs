@@ -0,0 +1,25 @@
+extern crate cargo_gen_helpers;
+extern crate tempdir;
+
+use cargo_gen_helpers::test_helpers::read_file_to_string;
+use cargo_gen_helpers::{create_file, ChangeSet};
+use std::env;
+use tempdir::TempDir;
+
+// The conflict policy is set for the whole process, so this is the only test here.
+#[test]
+fn it_overwrites_the_files_that_exist() {
+    let project = TempDir::new("cargo-gen-test").unwrap();
+    let root = project.path();
+    create_file(root.join("src/a.rs"), "old a").unwrap();
+    create_file(root.join("src/b.rs"), "old b").unwrap();
+    env::set_var("CARGO_GEN_CONFLICT", "overwrite");
+
+    create_file(root.join("src/a.rs"), "a").unwrap();
+    let mut changes = ChangeSet::new();
+    changes.create(root.join("src/b.rs"), "b");
+    changes.apply().unwrap();
+
+    assert_eq!("a", read_file_to_string(root.join("src/a.rs")).unwrap());
+    assert_eq!("b", read_file_to_string(root.join("src/b.rs")).unwrap());
+}
@@ -0,0 +1,38 @@
+extern crate cargo_gen_helpers;
+extern crate tempdir;
+
+use cargo_gen_helpers::test_helpers::{read_content, staged_path};
+use cargo_gen_helpers::{create_file, is_dry_run, modify_file, ChangeSet};
+use std::env;
+use tempdir::TempDir;
+
+// The staging directory is set for the whole process, so this is the only test here.
+#[test]
+fn it_writes_to_the_staging_directory_on_a_dry_run() {
+    let project = TempDir::new("cargo-gen-test").unwrap();
+    let root = project.path();
+    create_file(root.join("src/lib.rs"), "mod a;").unwrap();
+    create_file(root.join("src/old.rs"), "old").unwrap();
+    let staging = TempDir::new("cargo-gen-staging").unwrap();
+    env::set_var("CARGO_GEN_DRY_RUN", staging.path());
+    assert!(is_dry_run());
+
+    create_file(root.join("src/app.rs"), "app").unwrap();
+    modify_file(root.join("src/app.rs"), |content| Ok(Some(content + "!"))).unwrap();
+    modify_file(root.join("src/lib.rs"), |content| Ok(Some(content + "\nmod app;"))).unwrap();
+    let mut changes = ChangeSet::new();
+    changes
+        .delete(root.join("src/old.rs"))
+        .modify(root.join("src/app.rs"), |content| Ok(Some(content + "?")));
+    changes.apply().unwrap();
+
+    assert!(!root.join("src/app.rs").exists());
+    assert_eq!("mod a;", read_content(&root.join("src/lib.rs")));
+    assert_eq!("old", read_content(&root.join("src/old.rs")));
+    let staged_app = staged_path(&staging.path().join("files"), &root.join("src/app.rs"));
+    assert_eq!("app!?", read_content(&staged_app));
+    let staged_lib = staged_path(&staging.path().join("files"), &root.join("src/lib.rs"));
+    assert_eq!("mod a;\nmod app;", read_content(&staged_lib));
+    assert!(staged_path(&staging.path().join("removed"), &root.join("src/old.rs")).is_file());
+    assert!(modify_file(root.join("src/old.rs"), |_| Ok(None)).is_err());
+}
@@ -198,3 +198,19 @@ fn it_creates_a_cargo_generators_yaml_file() {
     let content = read_file_to_string(crate_dir.path().join("cargo_generators.yaml")).unwrap();
     assert!(content.contains("name: gen-test.app"));
 }
+
+#[test]
+fn it_generates_nothing_without_a_lib_rs() {
+    let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+    std::fs::remove_file(crate_dir.path().join("src/lib.rs")).unwrap();
+    let cargo_toml = read_toml(&crate_dir);
+    let generated = CargoGeneratorGenerator::from(args(&[
+        "app",
+        "--crate-root",
+        crate_dir.path().to_str().unwrap(),
+    ])).gen();
+    assert!(generated.is_err());
+    assert_eq!(cargo_toml, read_toml(&crate_dir));
+    assert!(!crate_dir.path().join("src/cargo_generators").exists());
+    assert!(!crate_dir.path().join("tests").exists());
+}
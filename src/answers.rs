@@ -0,0 +1,165 @@
+//! The answers of a run: the arguments a generator ran with, once the config applied its
+//! defaults. They are recorded in `.cargo-gen/answers/<generator>.toml` at the workspace root so
+//! that `cargo gen run --replay` can run the generator again the same way, e.g. after an upgrade.
+//! `cargo gen run --answers-file` takes a file of the same format:
+//!
+//! ```toml
+//! args = ["--crate-root", "app", "blog"]
+//! generator = "web.app"
+//! # The package of the generator when the answers were recorded, for information.
+//! package = "web-generators"
+//! version = "0.2.0"
+//! ```
+extern crate toml;
+
+use std::fs::{create_dir_all, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use failure::Error;
+use gen::Generator;
+use index::workspace_dir;
+
+/// Where the answers are recorded, relative to the workspace root.
+pub const ANSWERS_DIR: &str = ".cargo-gen/answers";
+
+#[derive(Debug, PartialEq)]
+pub struct Answers {
+    /// The name of the generator, not an alias.
+    pub generator: String,
+    pub package: Option<String>,
+    pub version: Option<String>,
+    pub args: Vec<String>,
+}
+
+/// The answers file of the generator named `name` in the workspace `root_crate_path` belongs to.
+pub fn answers_path(root_crate_path: &Path, name: &str) -> PathBuf {
+    workspace_dir(root_crate_path)
+        .join(ANSWERS_DIR)
+        .join(format!("{}.toml", name))
+}
+
+impl Answers {
+    pub fn of(generator: &Generator, args: &[String]) -> Answers {
+        let package = generator.package();
+        Answers {
+            generator: generator.name.clone(),
+            package: Some(package.name.clone()).filter(|name| !name.is_empty()),
+            version: Some(package.version.clone()).filter(|version| !version.is_empty()),
+            args: args.to_vec(),
+        }
+    }
+
+    /// The answers recorded for the generator named `name`.
+    pub fn load(root_crate_path: &Path, name: &str) -> Result<Answers, Error> {
+        let path = answers_path(root_crate_path, name);
+        if !path.is_file() {
+            bail!("No answers were recorded for {} in {}", name, path.display());
+        }
+        Answers::read(&path)
+    }
+
+    pub fn read(path: &Path) -> Result<Answers, Error> {
+        let mut toml_str = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut toml_str))
+            .map_err(|e| format_err!("{} could not be read: {}", path.display(), e))?;
+        let value = toml_str
+            .parse::<toml::Value>()
+            .map_err(|e| format_err!("{} is not valid: {}", path.display(), e))?;
+        let string_of = |key: &str| value.get(key).and_then(|v| v.as_str()).map(|s| s.to_owned());
+        let generator = string_of("generator")
+            .ok_or_else(|| format_err!("{} does not name the generator", path.display()))?;
+        let args = value
+            .get("args")
+            .and_then(|args| args.as_array())
+            .and_then(|args| {
+                args.iter()
+                    .map(|arg| arg.as_str().map(|arg| arg.to_owned()))
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| format_err!("The args of {} are not strings", path.display()))?;
+        Ok(Answers {
+            generator,
+            package: string_of("package"),
+            version: string_of("version"),
+            args,
+        })
+    }
+
+    /// Records the answers in the workspace `root_crate_path` belongs to, over the ones recorded
+    /// before.
+    pub fn save(&self, root_crate_path: &Path) -> Result<PathBuf, Error> {
+        let path = answers_path(root_crate_path, &self.generator);
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+        let mut table = toml::value::Table::new();
+        table.insert(
+            "generator".to_owned(),
+            toml::Value::String(self.generator.clone()),
+        );
+        let optional = [("package", &self.package), ("version", &self.version)];
+        for &(key, value) in &optional {
+            if let Some(value) = value {
+                table.insert(key.to_owned(), toml::Value::String(value.clone()));
+            }
+        }
+        table.insert(
+            "args".to_owned(),
+            toml::Value::Array(self.args.iter().cloned().map(toml::Value::String).collect()),
+        );
+        let content = format!(
+            "# This file is generated by cargo-gen. `cargo gen run --replay {}` runs the \
+             generator again with these arguments.\n{}",
+            self.generator,
+            toml::Value::Table(table)
+        );
+        File::create(&path)?.write_all(content.as_bytes())?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod answers_test {
+    extern crate cargo_gen_helpers;
+
+    use self::cargo_gen_helpers::create_file;
+    use self::cargo_gen_helpers::test_helpers::create_empty_crate;
+    use super::*;
+
+    #[test]
+    fn it_replays_the_answers_it_records() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        create_file(crate_dir.path().join("Cargo.lock"), "").unwrap();
+        let answers = Answers {
+            generator: "web.app".to_owned(),
+            package: Some("web-generators".to_owned()),
+            version: None,
+            args: vec!["--crate-root".to_owned(), "app".to_owned(), "blog".to_owned()],
+        };
+        let member_dir = crate_dir.path().join("member");
+        let path = answers.save(&member_dir).unwrap();
+        assert_eq!(crate_dir.path().join(ANSWERS_DIR).join("web.app.toml"), path);
+        assert_eq!(answers, Answers::load(&member_dir, "web.app").unwrap());
+    }
+
+    #[test]
+    fn it_reads_answers_written_by_hand() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let path = crate_dir.path().join("answers.toml");
+        create_file(&path, "generator = \"web.app\"\nargs = [\"blog\"]").unwrap();
+        assert_eq!(
+            Answers {
+                generator: "web.app".to_owned(),
+                package: None,
+                version: None,
+                args: vec!["blog".to_owned()],
+            },
+            Answers::read(&path).unwrap()
+        );
+
+        create_file(&path, "generator = \"web.app\"\nargs = [1]").unwrap();
+        assert!(Answers::read(&path).is_err());
+        assert!(Answers::load(crate_dir.path(), "web.model").is_err());
+    }
+}
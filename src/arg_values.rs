@@ -0,0 +1,577 @@
+//! The values of the generator arguments that do not come from the command line. From the
+//! highest precedence to the lowest:
+//!
+//! 1. the command line,
+//! 2. the answers recorded by a previous run, with `--replay` or `--answers-file` (see the
+//!    `answers` module),
+//! 3. the `CARGO_GEN_ARG_<NAME>` environment variables, e.g. `CARGO_GEN_ARG_CRATE_ROOT` for
+//!    `crate-root`. Flags take `true`, `false`, `1` or `0`, the values of the arguments that take
+//!    several are separated by spaces,
+//! 4. the `--args-file`, a JSON object or a TOML table (by the extension of the file) of values by
+//!    argument name. Flags take booleans, the arguments that take several values take arrays,
+//! 5. the defaults of the config, see the `config` module.
+//!
+//! The values are checked against the arguments of the generator before it is built: flags only
+//! take booleans, only the arguments declared `multiple` take several values and the ones with
+//! `possible_values` take nothing else.
+extern crate toml;
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use failure::Error;
+use serde_json::{self, Value};
+use config::{ArgDefault, Config};
+use gen::{Generator, GeneratorArg, ManifestError};
+
+/// The prefix of the environment variables that give arguments.
+pub const ARG_ENV_PREFIX: &str = "CARGO_GEN_ARG_";
+
+/// The arguments to run `generator` with: `args`, and the values of the recorded `answers`, of
+/// the environment, of `args_file` and of the config for the arguments that `args` leaves out.
+pub fn resolve(
+    generator: &Generator,
+    args: &[String],
+    answers: &[String],
+    args_file: Option<&Path>,
+    config: &Config,
+) -> Result<Vec<String>, Error> {
+    let from_answers = from_args(generator, answers)?;
+    let from_env = from_env(generator, |name| env::var(name).ok())?;
+    let from_file = match args_file {
+        Some(path) => from_file(generator, path)?,
+        None => vec![],
+    };
+    let layers = [from_answers, from_env, from_file, config.defaults(generator)?];
+    apply(generator, args, &layers)
+}
+
+/// The values that the command line arguments `args` give, e.g. the recorded answers of a run.
+pub fn from_args(generator: &Generator, args: &[String]) -> Result<Vec<ArgDefault>, Error> {
+    let mut values: Vec<ArgDefault> = Vec::new();
+    let mut give = |arg: &GeneratorArg, value: Option<String>| {
+        let index = match values.iter().position(|given| given.name == arg.name) {
+            Some(index) => index,
+            None => {
+                values.push(ArgDefault {
+                    name: arg.name.clone(),
+                    values: vec![],
+                });
+                values.len() - 1
+            }
+        };
+        values[index].values.extend(value);
+    };
+    // clap numbers the positional arguments without an index in the order they are declared.
+    let mut positionals = generator
+        .args()
+        .iter()
+        .filter(|arg| arg.is_positional())
+        .collect::<Vec<_>>();
+    positionals.sort_by_key(|arg| arg.index.unwrap_or(u64::MAX));
+    let mut positionals = positionals.into_iter().peekable();
+    let mut only_positionals = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if only_positionals || arg == "-" || !arg.starts_with('-') {
+            let positional = positionals.peek().cloned().ok_or_else(|| {
+                format_err!("{} has no positional argument for {}", generator.name, arg)
+            })?;
+            if !positional.multiple {
+                positionals.next();
+            }
+            give(positional, Some(arg.clone()));
+            continue;
+        }
+        if arg == "--" {
+            only_positionals = true;
+            continue;
+        }
+        // The option, its value if the argument holds it, and the flags before it in a cluster.
+        let (option, inline, flags) = if let Some(long) = arg.strip_prefix("--") {
+            let mut parts = long.splitn(2, '=');
+            let name = parts.next().unwrap_or_default();
+            let option = generator
+                .args()
+                .iter()
+                .find(|option| option.long.as_deref() == Some(name))
+                .ok_or_else(|| format_err!("{} has no option --{}", generator.name, name))?;
+            (option, parts.next(), vec![])
+        } else {
+            let (mut options, rest) = short_options(generator, &arg[1..]);
+            match options.pop() {
+                Some(option) if option.takes_value() || rest.is_empty() => {
+                    // clap takes `-c=app` as `-c app`.
+                    let value = rest.strip_prefix('=').unwrap_or(rest);
+                    (option, Some(value).filter(|value| !value.is_empty()), options)
+                }
+                _ => bail!("{} has no option {}", generator.name, arg),
+            }
+        };
+        for flag in flags {
+            give(flag, None);
+        }
+        let value = match inline {
+            _ if !option.takes_value() => None,
+            Some(value) => Some(value.to_owned()),
+            None => Some(
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| format_err!("{} has no value", option.name))?,
+            ),
+        };
+        give(option, value);
+    }
+    Ok(values)
+}
+
+/// The values of the `CARGO_GEN_ARG_<NAME>` variables that `var` finds.
+pub fn from_env<F>(generator: &Generator, var: F) -> Result<Vec<ArgDefault>, Error>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut values = Vec::new();
+    for arg in generator.args() {
+        let name = arg_env(&arg.name);
+        let value = match var(&name) {
+            Some(value) => value,
+            None => continue,
+        };
+        let value = if !arg.takes_value() {
+            match value.as_str() {
+                "true" | "1" => toml::Value::Boolean(true),
+                "false" | "0" => toml::Value::Boolean(false),
+                _ => toml::Value::String(value),
+            }
+        } else if arg.multiple {
+            toml::Value::Array(value.split_whitespace().map(toml::Value::from).collect())
+        } else {
+            toml::Value::String(value)
+        };
+        values.extend(values_of(arg, &value).map_err(|e| format_err!("{} {}", name, e))?);
+    }
+    Ok(values)
+}
+
+/// The values of the args file, JSON if its extension is `json` and TOML otherwise.
+pub fn from_file(generator: &Generator, path: &Path) -> Result<Vec<ArgDefault>, Error> {
+    let mut content = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut content))
+        .map_err(|e| ManifestError::new(path, e))?;
+    let table = if path.extension().map_or(false, |extension| extension == "json") {
+        serde_json::from_str::<Value>(&content)
+            .map_err(|e| ManifestError::new(path, e))?
+            .as_object()
+            .map(|object| {
+                object
+                    .iter()
+                    .map(|(name, value)| (name.clone(), toml_of(value)))
+                    .collect::<Vec<_>>()
+            })
+    } else {
+        match content.parse::<toml::Value>() {
+            Ok(toml::Value::Table(table)) => {
+                Some(table.into_iter().map(|(name, value)| (name, Some(value))).collect())
+            }
+            Ok(_) => None,
+            Err(e) => return Err(ManifestError::new(path, e).into()),
+        }
+    };
+    let table = table.ok_or_else(|| ManifestError::new(path, "The arguments are not a table"))?;
+    let mut values = Vec::new();
+    for (name, value) in table {
+        let arg = generator
+            .args()
+            .iter()
+            .find(|arg| arg.name == name)
+            .ok_or_else(|| {
+                ManifestError::new(path, format!("{} has no argument {}", generator.name, name))
+            })?;
+        let checked = match value {
+            Some(value) => values_of(arg, &value),
+            None => Err("is null".to_owned()),
+        };
+        values.extend(checked.map_err(|e| ManifestError::new(path, format!("{} {}", name, e)))?);
+    }
+    Ok(values)
+}
+
+/// The arguments to run the generator with: `args` after the options of `layers`, then the
+/// positional arguments of `layers` that `args` leaves out. Of the layers, the first that has a
+/// value for an argument gives it.
+pub fn apply(
+    generator: &Generator,
+    args: &[String],
+    layers: &[Vec<ArgDefault>],
+) -> Result<Vec<String>, Error> {
+    let value_of = |arg: &GeneratorArg| {
+        layers
+            .iter()
+            .filter_map(|layer| layer.iter().find(|value| value.name == arg.name))
+            .next()
+    };
+    // Values after `--` are positional whatever they look like.
+    let options = args.iter().take_while(|arg| *arg != "--").collect::<Vec<_>>();
+    let mut applied = Vec::new();
+    for arg in generator.args().iter().filter(|arg| !arg.is_positional()) {
+        let value = match value_of(arg) {
+            Some(value) => value,
+            None => continue,
+        };
+        if options.iter().any(|option| is_given(generator, arg, option)) {
+            continue;
+        }
+        let flag = match (arg.long.as_ref(), arg.short.as_ref()) {
+            (Some(long), _) => format!("--{}", long),
+            (None, Some(short)) => format!("-{}", short),
+            (None, None) => continue,
+        };
+        if value.values.is_empty() {
+            applied.push(flag.clone());
+        }
+        for value in &value.values {
+            applied.push(flag.clone());
+            applied.push(value.clone());
+        }
+    }
+    applied.extend(args.iter().cloned());
+
+    // clap numbers the positional arguments without an index in the order they are declared.
+    let mut positionals = generator
+        .args()
+        .iter()
+        .filter(|arg| arg.is_positional())
+        .collect::<Vec<_>>();
+    positionals.sort_by_key(|arg| arg.index.unwrap_or(u64::MAX));
+    let mut trailing = Vec::new();
+    let mut missing: Option<&GeneratorArg> = None;
+    for arg in positionals.into_iter().skip(positional_count(generator, args)) {
+        match (value_of(arg), missing) {
+            (Some(value), None) => trailing.extend(value.values.iter().cloned()),
+            (Some(_), Some(before)) => bail!(
+                "{} has a value but {} before it has none, give it on the command line",
+                arg.name,
+                before.name
+            ),
+            (None, _) => missing = missing.or(Some(arg)),
+        }
+    }
+    if !trailing.is_empty() {
+        if !args.iter().any(|arg| arg == "--") {
+            applied.push("--".to_owned());
+        }
+        applied.extend(trailing);
+    }
+    Ok(applied)
+}
+
+/// The values `value` gives to `arg` once checked, `None` for a flag that is not set. The errors
+/// are to follow the name of the argument.
+pub(crate) fn values_of(
+    arg: &GeneratorArg,
+    value: &toml::Value,
+) -> Result<Option<ArgDefault>, String> {
+    let values = match *value {
+        toml::Value::Boolean(set) if !arg.takes_value() => {
+            return Ok(Some(ArgDefault {
+                name: arg.name.clone(),
+                values: vec![],
+            }).filter(|_| set));
+        }
+        _ if !arg.takes_value() => return Err("is a flag, it can only be true or false".into()),
+        toml::Value::Array(ref values) => values
+            .iter()
+            .map(scalar)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| "has a nested value".to_owned())?,
+        ref value => vec![scalar(value).ok_or_else(|| "is not a value".to_owned())?],
+    };
+    if values.len() > 1 && !arg.multiple {
+        return Err("takes a single value".into());
+    }
+    let invalid = values.iter().find(|value| {
+        !arg.possible_values.is_empty() && !arg.possible_values.contains(value)
+    });
+    if let Some(invalid) = invalid {
+        return Err(format!(
+            "has the value {}, not one of {}",
+            invalid,
+            arg.possible_values.join(", ")
+        ));
+    }
+    Ok(Some(ArgDefault {
+        name: arg.name.clone(),
+        values,
+    }).filter(|value| !value.values.is_empty()))
+}
+
+/// E.g. `CARGO_GEN_ARG_CRATE_ROOT` for `crate-root`.
+fn arg_env(name: &str) -> String {
+    format!("{}{}", ARG_ENV_PREFIX, name.to_uppercase().replace('-', "_"))
+}
+
+/// The number of positional arguments in `args`.
+fn positional_count(generator: &Generator, args: &[String]) -> usize {
+    let takes_value = |matches: &dyn Fn(&GeneratorArg) -> bool| {
+        generator
+            .args()
+            .iter()
+            .any(|arg| !arg.is_positional() && arg.takes_value() && matches(arg))
+    };
+    let mut count = 0;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            return count + args.count();
+        }
+        let skips_next = if let Some(long) = arg.strip_prefix("--") {
+            takes_value(&|option| option.long.as_deref() == Some(long))
+        } else if let Some(shorts) = arg.strip_prefix('-').filter(|shorts| !shorts.is_empty()) {
+            let (options, rest) = short_options(generator, shorts);
+            rest.is_empty() && options.last().map_or(false, |option| option.takes_value())
+        } else {
+            count += 1;
+            false
+        };
+        if skips_next {
+            args.next();
+        }
+    }
+    count
+}
+
+/// The options a cluster of short options gives, e.g. `-f` and `-c` for `-fcapp` or `-fc`, and
+/// the rest of the cluster. The first short option that takes a value takes the rest as its
+/// value, the next argument if the rest is empty. Otherwise the rest starts at the first short
+/// option the generator does not have.
+fn short_options<'a, 'b>(
+    generator: &'a Generator,
+    shorts: &'b str,
+) -> (Vec<&'a GeneratorArg>, &'b str) {
+    let mut options = Vec::new();
+    for (i, short) in shorts.char_indices() {
+        let option = generator.args().iter().find(|arg| {
+            !arg.is_positional() && arg.short.as_ref().map_or(false, |s| s.chars().eq(Some(short)))
+        });
+        let option = match option {
+            Some(option) => option,
+            None => return (options, &shorts[i..]),
+        };
+        options.push(option);
+        if option.takes_value() {
+            return (options, &shorts[i + short.len_utf8()..]);
+        }
+    }
+    (options, "")
+}
+
+/// Whether the command line argument `option` gives `arg`, e.g. `--crate-root=app`, `-capp` or
+/// `-fc`.
+fn is_given(generator: &Generator, arg: &GeneratorArg, option: &str) -> bool {
+    if let Some(long) = option.strip_prefix("--") {
+        let long = long.split('=').next().unwrap_or_default();
+        return arg.long.as_deref() == Some(long);
+    }
+    match option.strip_prefix('-') {
+        Some(shorts) => short_options(generator, shorts)
+            .0
+            .iter()
+            .any(|option| option.name == arg.name),
+        None => false,
+    }
+}
+
+fn toml_of(json: &Value) -> Option<toml::Value> {
+    Some(match *json {
+        Value::Null => return None,
+        Value::Bool(value) => toml::Value::Boolean(value),
+        Value::Number(ref number) => match number.as_i64() {
+            Some(number) => toml::Value::Integer(number),
+            None => toml::Value::Float(number.as_f64()?),
+        },
+        Value::String(ref value) => toml::Value::String(value.clone()),
+        Value::Array(ref values) => {
+            toml::Value::Array(values.iter().map(toml_of).collect::<Option<_>>()?)
+        }
+        Value::Object(ref object) => toml::Value::Table(
+            object
+                .iter()
+                .map(|(key, value)| Some((key.clone(), toml_of(value)?)))
+                .collect::<Option<_>>()?,
+        ),
+    })
+}
+
+pub(crate) fn scalar(value: &toml::Value) -> Option<String> {
+    match *value {
+        toml::Value::String(ref value) => Some(value.clone()),
+        toml::Value::Integer(value) => Some(value.to_string()),
+        toml::Value::Float(value) => Some(value.to_string()),
+        toml::Value::Boolean(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod arg_values_test {
+    extern crate cargo_gen_helpers;
+    extern crate yaml_rust;
+
+    use std::collections::BTreeMap;
+    use self::cargo_gen_helpers::create_file;
+    use self::cargo_gen_helpers::test_helpers::create_empty_crate;
+    use self::yaml_rust::YamlLoader;
+    use super::*;
+
+    fn app() -> Generator {
+        Generator::try_from_yaml(
+            &YamlLoader::load_from_str(
+                "name: web.app\nfactory: f\nargs:\n  \
+                 - NAME:\n      index: 1\n  \
+                 - KIND:\n      index: 2\n      possible_values: [site, api]\n  \
+                 - crate-root:\n      long: crate-root\n      short: c\n      \
+                 value_name: FOLDER\n  \
+                 - feature:\n      long: feature\n      takes_value: true\n      \
+                 multiple: true\n  \
+                 - force:\n      long: force\n      short: f",
+            ).unwrap()[0],
+        ).unwrap()
+    }
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn value(name: &str, values: &[&str]) -> ArgDefault {
+        ArgDefault {
+            name: name.to_owned(),
+            values: strings(values),
+        }
+    }
+
+    #[test]
+    fn it_reads_the_arguments_from_the_environment() {
+        let vars = [
+            ("CARGO_GEN_ARG_CRATE_ROOT", "app"),
+            ("CARGO_GEN_ARG_FEATURE", "a b"),
+            ("CARGO_GEN_ARG_FORCE", "0"),
+        ].iter()
+            .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(
+            vec![value("crate-root", &["app"]), value("feature", &["a", "b"])],
+            from_env(&app(), |name| vars.get(name).cloned()).unwrap()
+        );
+        let err = from_env(&app(), |name| {
+            Some("yes".to_owned()).filter(|_| name == "CARGO_GEN_ARG_FORCE")
+        }).unwrap_err();
+        assert_eq!(
+            "CARGO_GEN_ARG_FORCE is a flag, it can only be true or false",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn it_reads_the_arguments_from_a_json_or_toml_file() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let json = crate_dir.path().join("args.json");
+        create_file(&json, r#"{"NAME": "blog", "feature": ["a", 1], "force": true}"#).unwrap();
+        assert_eq!(
+            vec![
+                value("NAME", &["blog"]),
+                value("feature", &["a", "1"]),
+                value("force", &[]),
+            ],
+            from_file(&app(), &json).unwrap()
+        );
+
+        let toml = crate_dir.path().join("args.toml");
+        create_file(&toml, "KIND = \"blog\"").unwrap();
+        let err = from_file(&app(), &toml).unwrap_err();
+        assert_eq!(
+            Some(&toml),
+            err.downcast_ref::<ManifestError>().map(|err| &err.path)
+        );
+        for content in &["crate-root = [\"a\", \"b\"]", "force = \"yes\"", "nothing = 1"] {
+            create_file(&toml, content).unwrap();
+            assert!(from_file(&app(), &toml).is_err(), "{}", content);
+        }
+    }
+
+    #[test]
+    fn it_gives_the_command_line_precedence() {
+        let layers = [
+            vec![value("crate-root", &["env"]), value("KIND", &["api"])],
+            vec![value("crate-root", &["file"]), value("NAME", &["blog"]), value("force", &[])],
+        ];
+        assert_eq!(
+            strings(&["--crate-root", "env", "--force", "--", "blog", "api"]),
+            apply(&app(), &[], &layers).unwrap()
+        );
+        assert_eq!(
+            strings(&["--force", "-c", "cli", "shop", "--", "api"]),
+            apply(&app(), &strings(&["-c", "cli", "shop"]), &layers).unwrap()
+        );
+        assert_eq!(
+            strings(&["--crate-root", "env", "--force", "--", "shop", "site"]),
+            apply(&app(), &strings(&["--force", "--", "shop", "site"]), &layers).unwrap()
+        );
+        assert!(apply(&app(), &[], &[vec![value("KIND", &["api"])]]).is_err());
+    }
+
+    #[test]
+    fn it_reads_the_values_of_recorded_arguments() {
+        let args = strings(&["-fcapp", "--feature=a", "--feature", "b", "--", "blog", "api"]);
+        assert_eq!(
+            vec![
+                value("force", &[]),
+                value("crate-root", &["app"]),
+                value("feature", &["a", "b"]),
+                value("NAME", &["blog"]),
+                value("KIND", &["api"]),
+            ],
+            from_args(&app(), &args).unwrap()
+        );
+        for args in &[&["--nothing"][..], &["-x"], &["--crate-root"], &["blog", "api", "more"]] {
+            assert!(from_args(&app(), &strings(args)).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn it_gives_the_command_line_precedence_over_the_answers() {
+        let answers = strings(&["--crate-root", "recorded", "--force", "blog"]);
+        assert_eq!(
+            strings(&["--force", "--crate-root", "cli", "--", "blog"]),
+            resolve(
+                &app(),
+                &strings(&["--crate-root", "cli"]),
+                &answers,
+                None,
+                &Config::default()
+            ).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_reads_the_clusters_of_short_options() {
+        let layers = [vec![
+            value("crate-root", &["env"]),
+            value("force", &[]),
+            value("NAME", &["blog"]),
+        ]];
+        assert_eq!(
+            strings(&["-fcapp", "--", "blog"]),
+            apply(&app(), &strings(&["-fcapp"]), &layers).unwrap()
+        );
+        assert_eq!(
+            strings(&["-fc", "app", "shop"]),
+            apply(&app(), &strings(&["-fc", "app", "shop"]), &layers).unwrap()
+        );
+        assert_eq!(
+            strings(&["--force", "-cfx", "--", "blog"]),
+            apply(&app(), &strings(&["-cfx"]), &layers).unwrap()
+        );
+    }
+}
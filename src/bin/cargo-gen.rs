@@ -1,19 +1,536 @@
+extern crate atty;
 extern crate cargo_gen;
+extern crate env_logger;
+extern crate failure;
+#[macro_use]
+extern crate log;
+extern crate term_size;
 
-use cargo_gen::cmd_args::CLArgs;
-use cargo_gen::gen::find_all;
+use cargo_gen::answers::Answers;
+use cargo_gen::arg_values::resolve;
+use cargo_gen::catalog::GeneratorCatalog;
+use cargo_gen::cmd_args::{CLArgs, ColorChoice, Command, ConfigArgs, DocsArgs, Interactive,
+                          MessageFormat, UndoArgs, ValidateArgs, Verbosity};
+use cargo_gen::completion::{candidates, script};
+use cargo_gen::config::{project_config_path, user_config_path, Config, PROJECT_CONFIG};
+use cargo_gen::docs::{man_page, man_page_name, markdown};
+use cargo_gen::dry_run::{unified_diff, Staging};
+use cargo_gen::gen::{parse_manifest, Generator};
+use cargo_gen::interactive::{pick_generator, prompt_args};
+use cargo_gen::journal::{undo, Snapshot};
+use cargo_gen::listing::{highlighted_table, info, json_lines, table, tree, Column};
+use cargo_gen::roots::install;
+use cargo_gen::runner::{run, Output};
+use cargo_gen::search::Pattern;
 use std::env::args_os;
 use std::env::current_dir;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::exit;
+use env_logger::Env;
+use env_logger::fmt::WriteStyle;
+use failure::Error;
+use log::LevelFilter;
+
+/// Filters the trace like `RUST_LOG` does, `--verbose` and `--quiet` take precedence.
+const LOG_ENV: &str = "CARGO_GEN_LOG";
+
+/// Discovery failed altogether, or the command itself failed.
+const EXIT_FATAL: i32 = 1;
+/// Some generator manifests are invalid, the generators of the others were found.
+const EXIT_INVALID_MANIFESTS: i32 = 2;
 
 fn main() {
     let clargs = CLArgs::parse(args_os());
-    if clargs.list {
-        // FIXME: panics
-        for result in find_all(current_dir().unwrap()) {
-            match result {
-                Ok(generator) => println!("{}", generator.name),
-                Err(err) => eprintln!("{}", err),
+    let cwd = current_dir().unwrap_or_else(|err| {
+        match clargs.command {
+            // Completion must not print anything but candidates, errors included.
+            Command::CompleteWords(_) => {}
+            _ => eprintln!("Cannot read the current directory: {}", err),
+        }
+        exit(EXIT_FATAL);
+    });
+    // Loaded once for the whole run, a broken config is reported where it matters.
+    let config = Config::load(&cwd);
+    // --color takes precedence over the config.
+    let color = clargs
+        .color
+        .or_else(|| config.as_ref().ok()?.color())
+        .unwrap_or_default();
+    init_logging(clargs.verbosity, color);
+    let status = match clargs.command {
+        Command::List => list_generators(&clargs, &cwd, &config),
+        Command::Info(ref id) => show_info(id, &clargs, &cwd, &config),
+        Command::Run => run_or_pick(&clargs, &cwd, &config, color),
+        Command::Validate(ref validate) => validate_manifests(validate, &clargs, &cwd, &config),
+        Command::Undo(ref undo_args) => undo_last_run(undo_args, &clargs, &cwd),
+        Command::Search(ref term) => search_generators(term, &clargs, &cwd, &config, color),
+        Command::Install(ref path) => install_crate(path),
+        Command::Docs(ref docs) => document_generators(docs, &clargs, &cwd, &config),
+        Command::Config(ref config_args) => show_config(config_args, &cwd, &config),
+        Command::Completions(ref shell) => print_completions(shell),
+        Command::CompleteWords(ref words) => print_candidates(words, &cwd, &config),
+    };
+    exit(status);
+}
+
+fn list_generators(clargs: &CLArgs, cwd: &Path, config: &Result<Config, Error>) -> i32 {
+    let catalog = GeneratorCatalog::load(cwd, clargs.refresh, config);
+    let generators = catalog
+        .iter()
+        .filter(|generator| clargs.all || (!generator.is_hidden() && generator.is_available()))
+        .filter(|generator| generator.matches(clargs.category.as_deref(), &clargs.tags))
+        .collect::<Vec<_>>();
+    match clargs.message_format {
+        MessageFormat::Human => {
+            if clargs.tree {
+                print!("{}", tree(&generators));
+            } else {
+                let width = term_size::dimensions_stdout().map(|(width, _)| width);
+                print!("{}", table(&generators, width, clargs.long));
+            }
+            report_errors(&catalog, clargs.verbosity);
+        }
+        MessageFormat::Json => print!("{}", json_lines(&generators, catalog.errors())),
+    }
+    discovery_status(&catalog)
+}
+
+fn show_info(id: &str, clargs: &CLArgs, cwd: &Path, config: &Result<Config, Error>) -> i32 {
+    let catalog = GeneratorCatalog::load(cwd, clargs.refresh, config);
+    exit_if_fatal(&catalog);
+    let generator = find_or_exit(&catalog, id);
+    let defaults = catalog.config().defaults(generator).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        vec![]
+    });
+    print!("{}", info(generator, &defaults));
+    0
+}
+
+/// Runs the generator of the command line or of the answers file, or the one picked
+/// interactively.
+fn run_or_pick(
+    clargs: &CLArgs,
+    cwd: &Path,
+    config: &Result<Config, Error>,
+    color: ColorChoice,
+) -> i32 {
+    let answers = clargs.answers_file.as_ref().map(|path| {
+        Answers::read(path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            exit(EXIT_FATAL);
+        })
+    });
+    let gen_id = clargs
+        .gen_id()
+        .or_else(|| answers.as_ref().map(|answers| answers.generator.as_str()));
+    if let Some(gen_id) = gen_id {
+        let catalog = GeneratorCatalog::load(cwd, clargs.refresh, config);
+        exit_if_fatal(&catalog);
+        if clargs.strict && !catalog.errors().is_empty() {
+            report_errors(&catalog, Verbosity::Normal);
+            eprintln!(
+                "Not running {} because discovery found errors and --strict is set",
+                gen_id
+            );
+            return EXIT_INVALID_MANIFESTS;
+        }
+        let generator = find_or_exit(&catalog, gen_id);
+        debug!(
+            "Chose generator {} of {} from {} for {}",
+            generator.name,
+            generator.package_id(),
+            generator.manifest_path().display(),
+            gen_id
+        );
+        if clargs.verbosity != Verbosity::Quiet && generator.name != gen_id {
+            eprintln!("{} is an alias of {}", gen_id, generator.name);
+        }
+        let recorded = match answers {
+            Some(answers) => answers.args,
+            None if clargs.replay => replayed_args(generator, cwd, clargs.verbosity),
+            None => vec![],
+        };
+        run_generator(&catalog, generator, cwd, clargs.gen_args(), &recorded, clargs, color);
+    }
+    let interactive = match clargs.interactive {
+        Interactive::No => false,
+        Interactive::IfTerminal => atty::is(atty::Stream::Stdin),
+        Interactive::Yes => true,
+    };
+    if !interactive {
+        if clargs.interactive == Interactive::IfTerminal {
+            eprint!("{}", CLArgs::help());
+            return EXIT_FATAL;
+        }
+        return 0;
+    }
+    let catalog = GeneratorCatalog::load(cwd, clargs.refresh, config);
+    exit_if_fatal(&catalog);
+    report_errors(&catalog, clargs.verbosity);
+    let generators = catalog
+        .iter()
+        .filter(|generator| !generator.is_hidden() && generator.is_available())
+        .collect::<Vec<_>>();
+    let (stdin, stdout) = (io::stdin(), io::stdout());
+    let (mut input, mut output) = (stdin.lock(), stdout.lock());
+    let answers = pick_generator(&generators, &mut input, &mut output).and_then(|generator| {
+        let args = prompt_args(generator, &mut input, &mut output)?;
+        Ok((generator, args))
+    });
+    match answers {
+        Ok((generator, args)) => {
+            if clargs.verbosity != Verbosity::Quiet {
+                let command = format!("cargo gen {} {}", generator.name, args.join(" "));
+                eprintln!("Running {}", command.trim_end());
+            }
+            run_generator(&catalog, generator, cwd, &args, &[], clargs, color);
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            EXIT_FATAL
+        }
+    }
+}
+
+fn validate_manifests(
+    validate: &ValidateArgs,
+    clargs: &CLArgs,
+    cwd: &Path,
+    config: &Result<Config, Error>,
+) -> i32 {
+    let catalog = match validate.manifest {
+        Some(ref manifest) => GeneratorCatalog::from(parse_manifest(manifest)),
+        None => GeneratorCatalog::load(cwd, clargs.refresh, config),
+    };
+    report_errors(&catalog, Verbosity::Normal);
+    if catalog.errors().is_empty() {
+        println!("{}", catalog.summary());
+    }
+    discovery_status(&catalog)
+}
+
+fn undo_last_run(undo_args: &UndoArgs, clargs: &CLArgs, cwd: &Path) -> i32 {
+    match undo(cwd, undo_args.force) {
+        Ok(journal) => {
+            for path in &journal.created {
+                println!("Removed {}", path.display());
+            }
+            for path in journal.changed.iter().chain(&journal.removed) {
+                println!("Restored {}", path.display());
             }
+            if clargs.verbosity != Verbosity::Quiet {
+                eprintln!("Undid the last run of {}", journal.generator);
+            }
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            EXIT_FATAL
+        }
+    }
+}
+
+fn search_generators(
+    term: &str,
+    clargs: &CLArgs,
+    cwd: &Path,
+    config: &Result<Config, Error>,
+    color: ColorChoice,
+) -> i32 {
+    let catalog = GeneratorCatalog::load(cwd, clargs.refresh, config);
+    let pattern = Pattern::new(term);
+    let generators = catalog
+        .iter()
+        .filter(|generator| clargs.all || (!generator.is_hidden() && generator.is_available()))
+        .filter(|generator| pattern.matches(generator))
+        .collect::<Vec<_>>();
+    if generators.is_empty() {
+        report_errors(&catalog, clargs.verbosity);
+        eprintln!("No generator matches {}", term);
+        return EXIT_FATAL;
+    }
+    let width = term_size::dimensions_stdout().map(|(width, _)| width);
+    if color.enabled(atty::is(atty::Stream::Stdout)) {
+        let highlight = |column: Column, cell: &str| match column {
+            Column::Id => pattern.highlight_id(cell),
+            Column::About => pattern.highlight_about(cell),
+        };
+        print!("{}", highlighted_table(&generators, width, false, &highlight));
+    } else {
+        print!("{}", table(&generators, width, false));
+    }
+    report_errors(&catalog, clargs.verbosity);
+    discovery_status(&catalog)
+}
+
+fn install_crate(path: &Path) -> i32 {
+    match install(path) {
+        Ok(link) => {
+            println!("Registered {} as {}", path.display(), link.display());
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            EXIT_FATAL
+        }
+    }
+}
+
+fn document_generators(
+    docs: &DocsArgs,
+    clargs: &CLArgs,
+    cwd: &Path,
+    config: &Result<Config, Error>,
+) -> i32 {
+    let catalog = GeneratorCatalog::load(cwd, clargs.refresh, config);
+    exit_if_fatal(&catalog);
+    let generators = catalog
+        .iter()
+        .filter(|generator| clargs.all || !generator.is_hidden())
+        .collect::<Vec<_>>();
+    if let Err(err) = write_docs(docs, &generators) {
+        eprintln!("{}", err);
+        return EXIT_FATAL;
+    }
+    report_errors(&catalog, clargs.verbosity);
+    discovery_status(&catalog)
+}
+
+fn show_config(config_args: &ConfigArgs, cwd: &Path, config: &Result<Config, Error>) -> i32 {
+    if !config_args.show {
+        let paths = user_config_path().into_iter().chain(Some(project_config_path(cwd)));
+        for path in paths {
+            let missing = if path.is_file() { "" } else { " (missing)" };
+            println!("{}{}", path.display(), missing);
+        }
+        return 0;
+    }
+    match *config {
+        Ok(ref config) => {
+            print!("{}", config.show());
+            0
+        }
+        Err(ref err) => {
+            eprintln!("{}", err);
+            EXIT_FATAL
+        }
+    }
+}
+
+fn print_completions(shell: &str) -> i32 {
+    match script(shell) {
+        Ok(script) => {
+            print!("{}", script);
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            EXIT_FATAL
+        }
+    }
+}
+
+/// Prints the candidates for the last of `words`, the word under the cursor.
+fn print_candidates(words: &[String], cwd: &Path, config: &Result<Config, Error>) -> i32 {
+    let catalog = GeneratorCatalog::load(cwd, false, config);
+    for candidate in candidates(&catalog, words) {
+        println!("{}", candidate);
+    }
+    0
+}
+
+/// Runs the generator with the arguments of the command line, of the recorded `answers`, of the
+/// environment, of the args file and of the config, records its changes for `cargo gen undo` and
+/// its answers if it succeeds, and exits with its status. On a dry run, prints what it would
+/// change instead.
+fn run_generator(
+    catalog: &GeneratorCatalog,
+    generator: &Generator,
+    root_crate_path: &Path,
+    args: &[String],
+    answers: &[String],
+    clargs: &CLArgs,
+    color: ColorChoice,
+) -> ! {
+    let (args_file, verbosity) = (clargs.args_file.as_deref(), clargs.verbosity);
+    if let Some(replacement) = generator.deprecated() {
+        if verbosity != Verbosity::Quiet {
+            eprintln!("Generator {} is deprecated: {}", generator.name, replacement);
+        }
+    }
+    if let Some(reason) = generator.unavailable_reason() {
+        eprintln!("{}", reason);
+        exit(EXIT_FATAL);
+    }
+    let config = catalog.config();
+    if clargs.dry_run {
+        let result = resolve(generator, args, answers, args_file, config).and_then(|args| {
+            let staging = Staging::create()?;
+            let output = Output::Staging(staging.dir());
+            let status = run(generator, root_crate_path, &args, config, output)?;
+            let changes = if status.success() { staging.changes()? } else { vec![] };
+            Ok((status, changes))
+        });
+        match result {
+            Ok((status, changes)) => {
+                let color = color.enabled(atty::is(atty::Stream::Stdout));
+                for change in &changes {
+                    let label = change.path.strip_prefix(root_crate_path).unwrap_or(&change.path);
+                    print!("{}", unified_diff(change, label, color));
+                }
+                if status.success() && changes.is_empty() && verbosity != Verbosity::Quiet {
+                    eprintln!("{} would not change any file", generator.name);
+                }
+                exit(status.code().unwrap_or(EXIT_FATAL))
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                exit(EXIT_FATAL);
+            }
+        }
+    }
+    let result = resolve(generator, args, answers, args_file, config).and_then(|args| {
+        let snapshot = Snapshot::take(root_crate_path);
+        let output = Output::Project(snapshot.as_ref().ok().map(|snapshot| snapshot.backup_dir()));
+        let status = run(generator, root_crate_path, &args, config, output)?;
+        match snapshot.and_then(|snapshot| snapshot.record(&generator.name)) {
+            Ok(ref journal) if journal.is_empty() => {}
+            Ok(_) => debug!("Recorded the changes of {} for cargo gen undo", generator.name),
+            Err(err) if verbosity != Verbosity::Quiet => {
+                eprintln!("The run cannot be undone: {}", err)
+            }
+            Err(_) => {}
+        }
+        Ok((args, status))
+    });
+    match result {
+        Ok((args, status)) => {
+            if status.success() {
+                match Answers::of(generator, &args).save(root_crate_path) {
+                    Ok(path) => debug!("Recorded the answers in {}", path.display()),
+                    Err(err) if verbosity != Verbosity::Quiet => {
+                        eprintln!("Could not record the answers: {}", err)
+                    }
+                    Err(_) => {}
+                }
+            }
+            exit(status.code().unwrap_or(EXIT_FATAL))
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            exit(EXIT_FATAL);
+        }
+    }
+}
+
+/// The arguments recorded for the generator, exits if there are none.
+fn replayed_args(
+    generator: &Generator,
+    root_crate_path: &Path,
+    verbosity: Verbosity,
+) -> Vec<String> {
+    let answers = Answers::load(root_crate_path, &generator.name).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(EXIT_FATAL);
+    });
+    let version = &generator.package().version;
+    if let (Some(recorded), false) = (answers.version.as_ref(), version.is_empty()) {
+        if recorded != version && verbosity != Verbosity::Quiet {
+            eprintln!(
+                "The answers were recorded with version {} of {}, replaying them with {}",
+                recorded,
+                generator.package().name,
+                version
+            );
+        }
+    }
+    answers.args
+}
+
+fn init_logging(verbosity: Verbosity, color: ColorChoice) {
+    let mut builder = env_logger::Builder::from_env(Env::new().filter(LOG_ENV));
+    builder.default_format_timestamp(false);
+    builder.write_style(match color {
+        ColorChoice::Auto => WriteStyle::Auto,
+        ColorChoice::Always => WriteStyle::Always,
+        ColorChoice::Never => WriteStyle::Never,
+    });
+    if verbosity == Verbosity::Verbose {
+        builder.filter_module("cargo_gen", LevelFilter::Debug);
+    }
+    builder.init();
+    // The directives of `CARGO_GEN_LOG` for modules of cargo gen would win over a filter on the
+    // whole crate, the global level wins over all of them.
+    if verbosity == Verbosity::Quiet {
+        log::set_max_level(LevelFilter::Off);
+    }
+}
+
+fn write_docs(docs: &DocsArgs, generators: &[&Generator]) -> io::Result<()> {
+    let markdown = markdown(generators);
+    match docs.output {
+        Some(ref output) => fs::write(output, markdown)?,
+        None => print!("{}", markdown),
+    }
+    if let Some(ref man_dir) = docs.man_dir {
+        fs::create_dir_all(man_dir)?;
+        for generator in generators {
+            fs::write(man_dir.join(man_page_name(generator)), man_page(generator))?;
+        }
+    }
+    Ok(())
+}
+
+/// Prints the discovery errors followed by a summary, if there are errors. Quiet runs only print
+/// the errors that stop discovery.
+fn report_errors(catalog: &GeneratorCatalog, verbosity: Verbosity) {
+    if catalog.errors().is_empty() || (verbosity == Verbosity::Quiet && !catalog.is_fatal()) {
+        return;
+    }
+    for err in catalog.errors() {
+        eprintln!("{}", err);
+    }
+    eprintln!("{}", catalog.summary());
+}
+
+fn discovery_status(catalog: &GeneratorCatalog) -> i32 {
+    if catalog.is_fatal() {
+        EXIT_FATAL
+    } else if !catalog.errors().is_empty() {
+        EXIT_INVALID_MANIFESTS
+    } else {
+        0
+    }
+}
+
+fn exit_if_fatal(catalog: &GeneratorCatalog) {
+    if catalog.is_fatal() {
+        report_errors(catalog, Verbosity::Normal);
+        exit(EXIT_FATAL);
+    }
+}
+
+fn find_or_exit<'a>(catalog: &'a GeneratorCatalog, id: &str) -> &'a Generator {
+    match catalog.get(id) {
+        Some(generator) => generator,
+        None if catalog.get_disabled(id).is_some() => {
+            let config = catalog.config();
+            let path = config
+                .source("disabled")
+                .or_else(|| config.source("disabled-packages"))
+                .unwrap_or_else(|| Path::new(PROJECT_CONFIG));
+            eprintln!("Generator {} is disabled in {}", id, path.display());
+            exit(EXIT_FATAL);
+        }
+        None => {
+            eprintln!(
+                "Generator {} not found. Run `cargo gen list` to see the available ones.",
+                id
+            );
+            exit(EXIT_FATAL);
         }
     }
 }
@@ -0,0 +1,323 @@
+//! The generators of a project, for tools that want to show them without parsing the generator
+//! manifests again.
+use std::ffi::OsStr;
+use std::path::Path;
+use std::slice;
+use failure::{err_msg, Error};
+use config::Config;
+use gen::{find_all_cached, DiscoveryError, Generator, ManifestError};
+
+/// The generators found for a project and the errors met while looking for them.
+#[derive(Debug)]
+pub struct GeneratorCatalog {
+    generators: Vec<Generator>,
+    /// The generators the config disables.
+    disabled: Vec<Generator>,
+    errors: Vec<Error>,
+    config: Config,
+}
+
+/// The generators one package defines.
+#[derive(Debug)]
+pub struct PackageGenerators<'a> {
+    /// The package id as reported by `cargo metadata`.
+    pub package_id: &'a str,
+    /// The `cargo_generators.yaml` of the package.
+    pub manifest_path: &'a Path,
+    pub generators: Vec<&'a Generator>,
+}
+
+impl GeneratorCatalog {
+    /// Discovers the generators available to the crate at `root_crate_path`, through the
+    /// discovery index unless `refresh` is set, and applies `config`, the user and project config
+    /// loaded for the run. A broken config is one of the errors, discovery goes on without it.
+    pub fn load<P>(
+        root_crate_path: P,
+        refresh: bool,
+        config: &Result<Config, Error>,
+    ) -> GeneratorCatalog
+    where
+        P: AsRef<Path> + AsRef<OsStr>,
+    {
+        let default = Config::default();
+        let applied = config.as_ref().unwrap_or(&default);
+        let mut catalog =
+            GeneratorCatalog::from(find_all_cached(&root_crate_path, refresh, applied));
+        match *config {
+            Ok(ref config) => catalog.configure(config.clone()),
+            Err(ref e) => catalog.errors.push(match e.downcast_ref::<ManifestError>() {
+                Some(e) => e.clone().into(),
+                None => err_msg(e.to_string()),
+            }),
+        }
+        catalog
+    }
+
+    /// Applies the config: sets the generators it disables aside.
+    pub fn configure(&mut self, config: Config) {
+        let generators = self.generators.drain(..).collect::<Vec<_>>();
+        for generator in generators {
+            if config.is_disabled(&generator) {
+                debug!("Skipped generator {}: disabled in the config", generator.name);
+                self.disabled.push(generator);
+            } else {
+                self.generators.push(generator);
+            }
+        }
+        self.config = config;
+    }
+
+    /// The config applied to the generators, the default one until `configure` is called.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Looks a generator the config disables up by its name or by one of its aliases.
+    pub fn get_disabled(&self, id: &str) -> Option<&Generator> {
+        self.disabled
+            .iter()
+            .find(|generator| generator.is_known_as(id))
+    }
+
+    /// Looks a generator up by its name or by one of its aliases.
+    pub fn get(&self, id: &str) -> Option<&Generator> {
+        self.generators
+            .iter()
+            .find(|generator| generator.is_known_as(id))
+    }
+
+    /// All the generators in discovery order.
+    pub fn iter(&self) -> slice::Iter<'_, Generator> {
+        self.generators.iter()
+    }
+
+    /// The generators grouped by the package that defines them, in discovery order.
+    pub fn packages(&self) -> Vec<PackageGenerators<'_>> {
+        let mut packages: Vec<PackageGenerators> = Vec::new();
+        for generator in &self.generators {
+            let position = packages
+                .iter()
+                .position(|package| package.manifest_path == generator.manifest_path());
+            match position {
+                Some(position) => packages[position].generators.push(generator),
+                None => packages.push(PackageGenerators {
+                    package_id: generator.package_id(),
+                    manifest_path: generator.manifest_path(),
+                    generators: vec![generator],
+                }),
+            }
+        }
+        packages
+    }
+
+    /// Invalid generator manifests, duplicate generators and the like. The generators concerned
+    /// are not in the catalog.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+
+    /// Whether discovery failed altogether rather than for some manifests.
+    pub fn is_fatal(&self) -> bool {
+        self.errors
+            .iter()
+            .any(|err| err.downcast_ref::<DiscoveryError>().is_some())
+    }
+
+    /// The number of distinct generator manifests with errors.
+    pub fn manifests_with_errors(&self) -> usize {
+        let mut paths = self
+            .errors
+            .iter()
+            .filter_map(|err| err.downcast_ref::<ManifestError>())
+            .map(|err| &err.path)
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+        paths.len()
+    }
+
+    /// E.g. `12 generators found, 2 manifests with errors`.
+    pub fn summary(&self) -> String {
+        let mut summary = plural(self.len(), "generator", "generators") + " found";
+        let manifests = self.manifests_with_errors();
+        if manifests > 0 {
+            summary += &format!(", {} with errors", plural(manifests, "manifest", "manifests"));
+        }
+        let others = self
+            .errors
+            .iter()
+            .filter(|err| err.downcast_ref::<ManifestError>().is_none())
+            .count();
+        if others > 0 {
+            summary += &format!(", {}", plural(others, "other error", "other errors"));
+        }
+        summary
+    }
+
+    pub fn len(&self) -> usize {
+        self.generators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.generators.is_empty()
+    }
+}
+
+fn plural(count: usize, one: &str, many: &str) -> String {
+    format!("{} {}", count, if count == 1 { one } else { many })
+}
+
+impl From<Vec<Result<Generator, Error>>> for GeneratorCatalog {
+    fn from(results: Vec<Result<Generator, Error>>) -> GeneratorCatalog {
+        let mut generators = Vec::new();
+        let mut errors = Vec::new();
+        for result in results {
+            match result {
+                Ok(generator) => generators.push(generator),
+                Err(err) => errors.push(err),
+            }
+        }
+        GeneratorCatalog {
+            generators,
+            disabled: vec![],
+            errors,
+            config: Config::default(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a GeneratorCatalog {
+    type Item = &'a Generator;
+    type IntoIter = slice::Iter<'a, Generator>;
+
+    fn into_iter(self) -> slice::Iter<'a, Generator> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod catalog_test {
+    extern crate cargo_gen_helpers;
+
+    use self::cargo_gen_helpers::test_helpers::create_empty_crate;
+    use self::cargo_gen_helpers::{create_file, modify_file};
+    use gen::find_all;
+    use roots::isolate_home;
+    use super::*;
+
+    #[test]
+    fn it_looks_generators_up_by_name_and_alias() {
+        isolate_home();
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        create_file(
+            crate_dir.path().join("cargo_generators.yaml"),
+            "- name: root.gen1\n  factory: f\n  aliases: [root.old]\n- name: root.gen2\n  \
+             factory: f",
+        ).unwrap();
+        let catalog = GeneratorCatalog::from(find_all(crate_dir.path(), &Config::default()));
+        assert_eq!(2, catalog.len());
+        assert_eq!("root.gen1", catalog.get("root.old").unwrap().name);
+        assert_eq!("root.gen2", catalog.get("root.gen2").unwrap().name);
+        assert!(catalog.get("root.gen3").is_none());
+    }
+
+    #[test]
+    fn it_keeps_the_errors_apart() {
+        isolate_home();
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        create_file(
+            crate_dir.path().join("cargo_generators.yaml"),
+            "- name: root.gen1\n  factory: f\n- name: root.gen1\n  factory: f\n- factory: f",
+        ).unwrap();
+        let catalog = GeneratorCatalog::from(find_all(crate_dir.path(), &Config::default()));
+        assert_eq!(
+            vec!["root.gen1"],
+            catalog.iter().map(|g| g.name.as_str()).collect::<Vec<_>>()
+        );
+        assert_eq!(2, catalog.errors().len());
+        assert!(!catalog.is_fatal());
+        assert_eq!(1, catalog.manifests_with_errors());
+        assert_eq!(
+            "1 generator found, 1 manifest with errors",
+            catalog.summary()
+        );
+    }
+
+    #[test]
+    fn it_sets_the_disabled_generators_aside() {
+        isolate_home();
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        create_file(
+            crate_dir.path().join("cargo_generators.yaml"),
+            "- name: root.gen1\n  factory: f\n- name: root.gen2\n  factory: f",
+        ).unwrap();
+        create_file(
+            crate_dir.path().join(".cargo-gen.toml"),
+            "disabled = [\"root.gen2\"]",
+        ).unwrap();
+        let config = Config::load(crate_dir.path());
+        let catalog = GeneratorCatalog::load(crate_dir.path(), true, &config);
+        assert_eq!(
+            vec!["root.gen1"],
+            catalog.iter().map(|g| g.name.as_str()).collect::<Vec<_>>()
+        );
+        assert!(catalog.get("root.gen2").is_none());
+        assert!(catalog.get_disabled("root.gen2").is_some());
+        assert_eq!(
+            Some(crate_dir.path().join(".cargo-gen.toml").as_path()),
+            catalog.config().source("disabled")
+        );
+    }
+
+    #[test]
+    fn it_tells_a_failed_discovery_apart() {
+        isolate_home();
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        create_file(crate_dir.path().join("Cargo.toml"), "[package]").unwrap();
+        let catalog = GeneratorCatalog::from(find_all(crate_dir.path(), &Config::default()));
+        assert!(catalog.is_fatal());
+        assert_eq!("0 generators found, 1 other error", catalog.summary());
+    }
+
+    #[test]
+    fn it_groups_the_generators_by_package() {
+        isolate_home();
+        let dep_crate_dir = create_empty_crate("cargo-gen-dep").unwrap();
+        create_file(
+            dep_crate_dir.path().join("cargo_generators.yaml"),
+            "- name: dep.gen1\n  factory: f\n- name: dep.gen2\n  factory: f",
+        ).unwrap();
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        modify_file(crate_dir.path().join("Cargo.toml"), |contents| {
+            let deps_str = "[dependencies]\n";
+            Ok(Some(contents.replace(
+                deps_str,
+                &format!(
+                    "{}cargo-gen-dep = {{ path = \"{}\" }}\n",
+                    deps_str,
+                    dep_crate_dir.path().display()
+                ),
+            )))
+        }).unwrap();
+        create_file(
+            crate_dir.path().join("cargo_generators.yaml"),
+            "- name: root.gen1\n  factory: f",
+        ).unwrap();
+
+        let catalog = GeneratorCatalog::from(find_all(crate_dir.path(), &Config::default()));
+        let mut packages = catalog
+            .packages()
+            .into_iter()
+            .map(|package| {
+                assert!(!package.package_id.is_empty());
+                package
+                    .generators
+                    .iter()
+                    .map(|g| g.name.as_str())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        packages.sort();
+        assert_eq!(vec![vec!["dep.gen1", "dep.gen2"], vec!["root.gen1"]], packages);
+    }
+}
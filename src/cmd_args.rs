@@ -1,63 +1,632 @@
 extern crate clap;
 
 use std::ffi::OsString;
-use self::clap::{App, AppSettings, Arg, SubCommand};
+use std::path::PathBuf;
+use self::clap::{App, AppSettings, Arg, Error, ErrorKind, SubCommand};
 
+/// How `cargo gen list` prints the generators.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MessageFormat {
+    Human,
+    /// One JSON object per line.
+    Json,
+}
+
+impl Default for MessageFormat {
+    fn default() -> MessageFormat {
+        MessageFormat::Human
+    }
+}
+
+/// How much `cargo gen` writes to the standard error besides the errors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verbosity {
+    /// No notes, warnings nor non-fatal discovery errors.
+    Quiet,
+    Normal,
+    /// Also trace the discovery of the generators.
+    Verbose,
+}
+
+impl Default for Verbosity {
+    fn default() -> Verbosity {
+        Verbosity::Normal
+    }
+}
+
+/// When to use terminal colours, for the search highlights, the dry-run diffs and the trace.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorChoice {
+    /// Only when writing to a terminal.
+    Auto,
+    Always,
+    Never,
+}
+
+impl Default for ColorChoice {
+    fn default() -> ColorChoice {
+        ColorChoice::Auto
+    }
+}
+
+impl ColorChoice {
+    /// Whether to colour the output of a stream that is or is not a terminal.
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => is_terminal,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+
+    /// The choice named `auto`, `always` or `never`, as `--color` and the config take it.
+    pub fn from_name(name: &str) -> Option<ColorChoice> {
+        match name {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `cargo gen` prompts for a generator to run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interactive {
+    No,
+    /// `cargo gen` alone, when the standard input is a terminal. The help is printed otherwise.
+    IfTerminal,
+    /// `cargo gen run --interactive`, also when the answers come from a pipe.
+    Yes,
+}
+
+impl Default for Interactive {
+    fn default() -> Interactive {
+        Interactive::No
+    }
+}
+
+/// Where `cargo gen docs` writes the documentation.
+#[derive(Debug, Default, PartialEq)]
+pub struct DocsArgs {
+    /// The Markdown file, the standard output if `None`.
+    pub output: Option<PathBuf>,
+    /// The directory to also write a man page per generator to.
+    pub man_dir: Option<PathBuf>,
+}
+
+/// What `cargo gen config` prints.
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigArgs {
+    /// The merged config and the file each value comes from, the config files otherwise.
+    pub show: bool,
+}
+
+/// What `cargo gen validate` checks.
+#[derive(Debug, Default, PartialEq)]
+pub struct ValidateArgs {
+    /// A generator manifest to check on its own, the manifests of the project otherwise.
+    pub manifest: Option<PathBuf>,
+}
+
+/// How `cargo gen undo` undoes the last run.
+#[derive(Debug, Default, PartialEq)]
+pub struct UndoArgs {
+    /// Also when the files of the run changed since.
+    pub force: bool,
+}
+
+/// What `cargo gen` does, with the arguments of the subcommands that take their own.
 #[derive(Debug, PartialEq)]
+pub enum Command {
+    /// `cargo gen list`, or the hidden `--list` flag.
+    List,
+    /// Shows the details of a generator.
+    Info(String),
+    /// `cargo gen run`, `cargo gen GENERATOR` and `cargo gen` alone.
+    Run,
+    Validate(ValidateArgs),
+    Undo(UndoArgs),
+    Search(String),
+    /// Registers the generator crate at a path.
+    Install(PathBuf),
+    Docs(DocsArgs),
+    Config(ConfigArgs),
+    /// Prints the completion script of a shell.
+    Completions(String),
+    /// The words of the command line being completed, the last one is the word under the cursor.
+    CompleteWords(Vec<String>),
+}
+
+impl Default for Command {
+    fn default() -> Command {
+        Command::Run
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
 pub struct CLArgs {
-    pub list: bool,
+    pub command: Command,
+    pub all: bool,
+    pub long: bool,
+    pub tree: bool,
+    pub message_format: MessageFormat,
+    pub refresh: bool,
+    pub strict: bool,
+    pub dry_run: bool,
+    pub replay: bool,
+    pub answers_file: Option<PathBuf>,
+    pub args_file: Option<PathBuf>,
+    pub verbosity: Verbosity,
+    /// `None` unless `--color` is given, the config decides then.
+    pub color: Option<ColorChoice>,
+    pub interactive: Interactive,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
     gen_id: Option<String>,
     gen_args: Vec<String>,
 }
 
 impl CLArgs {
-    pub fn parse<I, T>(args: I) -> CLArgs
-    where
-        I: IntoIterator<Item = T>,
-        T: Into<OsString> + Clone,
-    {
-        let args = App::new("")
+    /// The command line interface of `cargo gen`, also used to generate the shell completions.
+    pub fn app() -> App<'static, 'static> {
+        App::new("")
             .setting(AppSettings::SubcommandRequiredElseHelp)
             .subcommand(
                 SubCommand::with_name("gen")
-                    .settings(&[
-                        AppSettings::ArgRequiredElseHelp,
-                        AppSettings::AllowExternalSubcommands,
-                    ])
+                    // `cargo gen GENERATOR [ARGS]...` is short for `cargo gen run`.
+                    .setting(AppSettings::AllowExternalSubcommands)
+                    .after_help(
+                        "A generator can also be run with `cargo gen GENERATOR [ARGS]...` unless \
+                         its name is one of the subcommands. Without a subcommand cargo gen asks \
+                         for a generator to run when the standard input is a terminal.",
+                    )
+                    .arg(
+                        Arg::with_name("refresh")
+                            .help(
+                                "Rebuild the cached discovery index and move the generator packs \
+                                 that follow a branch to its latest commit",
+                            )
+                            .long("refresh")
+                            .global(true),
+                    )
                     .arg(
-                        Arg::with_name("list")
-                            .help("List the available generators")
-                            .long("list")
-                            .short("l")
-                            .conflicts_with("GENERATOR_NAME"),
+                        Arg::with_name("quiet")
+                            .help("Only print the errors that stop cargo gen")
+                            .long("quiet")
+                            .short("q")
+                            .conflicts_with("verbose")
+                            .global(true),
+                    )
+                    .arg(
+                        Arg::with_name("verbose")
+                            .help(
+                                "Trace how the generators are discovered and chosen, the trace \
+                                 can also be filtered through CARGO_GEN_LOG (e.g. \
+                                 CARGO_GEN_LOG=cargo_gen=debug)",
+                            )
+                            .long("verbose")
+                            .short("v")
+                            .global(true),
+                    )
+                    .arg(
+                        Arg::with_name("color")
+                            .help("When to use colours [default: auto]")
+                            .long("color")
+                            .value_name("WHEN")
+                            .possible_values(&["auto", "always", "never"])
+                            .global(true),
+                    )
+                    .arg(
+                        // What `list` was before the subcommands, for the scripts that use it.
+                        Arg::with_name("list").long("list").short("l").hidden(true),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("list")
+                            .about("List the available generators")
+                            .arg(
+                                Arg::with_name("all")
+                                    .help("Also list hidden and unavailable generators")
+                                    .long("all")
+                                    .short("a"),
+                            )
+                            .arg(
+                                Arg::with_name("long")
+                                    .help("Also list the arguments of each generator")
+                                    .long("long"),
+                            )
+                            .arg(
+                                Arg::with_name("tree")
+                                    .help("Group the generators by the package that provides them")
+                                    .long("tree")
+                                    .conflicts_with("long"),
+                            )
+                            .arg(
+                                Arg::with_name("message-format")
+                                    .help("The output format of the list [default: human]")
+                                    .long("message-format")
+                                    .alias("format")
+                                    .value_name("FMT")
+                                    .possible_values(&["human", "json"]),
+                            )
+                            .arg(
+                                Arg::with_name("category")
+                                    .help("Only list the generators of the category")
+                                    .long("category")
+                                    .value_name("CATEGORY")
+                                    .takes_value(true),
+                            )
+                            .arg(
+                                Arg::with_name("tag")
+                                    .help("Only list the generators with the tag, can be repeated")
+                                    .long("tag")
+                                    .value_name("TAG")
+                                    .takes_value(true)
+                                    .multiple(true)
+                                    .number_of_values(1),
+                            ),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("info")
+                            .about("Show everything known about a generator")
+                            .arg(
+                                Arg::with_name("GENERATOR")
+                                    .help("The name or an alias of the generator")
+                                    .required(true)
+                                    .index(1),
+                            ),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("run")
+                            .about("Run a generator")
+                            .setting(AppSettings::TrailingVarArg)
+                            .arg(
+                                Arg::with_name("strict")
+                                    .help(
+                                        "Refuse to run the generator if any generator manifest is \
+                                         invalid",
+                                    )
+                                    .long("strict"),
+                            )
+                            .arg(
+                                Arg::with_name("dry-run")
+                                    .help(
+                                        "Print a diff of the files the generator would create, \
+                                         change or remove instead of writing them, \
+                                         with cargo-gen-helpers 0.0.2 or later",
+                                    )
+                                    .long("dry-run"),
+                            )
+                            .arg(
+                                Arg::with_name("interactive")
+                                    .help(
+                                        "Pick a generator and answer its arguments line by line, \
+                                         also when the standard input is not a terminal",
+                                    )
+                                    .long("interactive")
+                                    .short("i")
+                                    .conflicts_with_all(&["GENERATOR", "answers-file"]),
+                            )
+                            .arg(
+                                Arg::with_name("replay")
+                                    .help(
+                                        "Run the generator with the answers recorded in \
+                                         .cargo-gen/answers, the arguments given take \
+                                         precedence",
+                                    )
+                                    .long("replay")
+                                    .requires("GENERATOR")
+                                    .conflicts_with("answers-file"),
+                            )
+                            .arg(
+                                Arg::with_name("args-file")
+                                    .help(
+                                        "Read the arguments the command line leaves out from a \
+                                         JSON or TOML file, CARGO_GEN_ARG_<NAME> variables take \
+                                         precedence",
+                                    )
+                                    .long("args-file")
+                                    .value_name("FILE"),
+                            )
+                            .arg(
+                                Arg::with_name("answers-file")
+                                    .help(
+                                        "Run the generator with the answers of the file, the \
+                                         generator it names unless one is given",
+                                    )
+                                    .long("answers-file")
+                                    .value_name("FILE"),
+                            )
+                            .arg(
+                                // One argument so that the arguments of the generator are never
+                                // taken for the options of `run`.
+                                Arg::with_name("GENERATOR")
+                                    .help(
+                                        "The name or an alias of the generator, followed by its \
+                                         arguments",
+                                    )
+                                    .required_unless_one(&["interactive", "answers-file"])
+                                    .multiple(true)
+                                    .allow_hyphen_values(true)
+                                    .index(1),
+                            ),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("validate")
+                            .about(
+                                "Check the generator manifests of the project, or the one given",
+                            )
+                            .arg(
+                                Arg::with_name("MANIFEST")
+                                    .help("A cargo_generators.yaml to check on its own")
+                                    .index(1),
+                            ),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("undo")
+                            .about("Undo the changes of the last generator run")
+                            .arg(
+                                Arg::with_name("force")
+                                    .help("Also undo the files that changed since the run")
+                                    .long("force")
+                                    .short("f"),
+                            ),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("search")
+                            .about("List the generators that match a term")
+                            .arg(
+                                Arg::with_name("TERM")
+                                    .help(
+                                        "A part of the generator id, a glob on it such as \
+                                         `web.*`, or a word of the about text or a tag",
+                                    )
+                                    .required(true)
+                                    .index(1),
+                            )
+                            .arg(
+                                Arg::with_name("all")
+                                    .help("Also search hidden and unavailable generators")
+                                    .long("all")
+                                    .short("a"),
+                            ),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("install")
+                            .about("Register a local generator crate for all projects")
+                            .arg(
+                                Arg::with_name("PATH")
+                                    .help("The root folder of the generator crate")
+                                    .required(true)
+                                    .index(1),
+                            ),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("docs")
+                            .about("Write the documentation of the generators as Markdown")
+                            .arg(
+                                Arg::with_name("output")
+                                    .help("The file to write to instead of the standard output")
+                                    .long("output")
+                                    .short("o")
+                                    .value_name("FILE"),
+                            )
+                            .arg(
+                                Arg::with_name("man")
+                                    .help("Also write a man page per generator to the directory")
+                                    .long("man")
+                                    .value_name("DIR"),
+                            )
+                            .arg(
+                                Arg::with_name("all")
+                                    .help("Also document the hidden generators")
+                                    .long("all")
+                                    .short("a"),
+                            ),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("config")
+                            .about("Print where the config files of cargo gen are")
+                            .arg(
+                                Arg::with_name("show")
+                                    .help(
+                                        "Print the merged config instead, with the file each \
+                                         value comes from",
+                                    )
+                                    .long("show"),
+                            ),
+                    )
+                    .subcommand(
+                        SubCommand::with_name("completions")
+                            .about("Print a shell completion script for cargo-gen")
+                            .arg(
+                                Arg::with_name("SHELL")
+                                    .help("The shell to complete in")
+                                    .possible_values(&["bash", "zsh", "fish"])
+                                    .required(true)
+                                    .index(1),
+                            ),
+                    )
+                    .subcommand(
+                        // Called back by the completion scripts.
+                        SubCommand::with_name("complete-words")
+                            .setting(AppSettings::Hidden)
+                            .arg(
+                                Arg::with_name("WORDS")
+                                    .multiple(true)
+                                    .allow_hyphen_values(true)
+                                    .last(true),
+                            ),
                     ),
             )
-            .get_matches_from(args);
+    }
+
+    pub fn parse<I, T>(args: I) -> CLArgs
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let args = CLArgs::app().get_matches_from(args);
         let gen_args = args.subcommand_matches("gen").unwrap();
-        match gen_args.subcommand() {
-            (subcmd, Some(subcmd_args)) => {
-                let subcmd_args = match subcmd_args.values_of("") {
+        let (subcmd, subcmd_args) = gen_args.subcommand();
+        // The global arguments given after a subcommand only show in its matches.
+        let is_present = |name: &str| {
+            gen_args.is_present(name) || subcmd_args.map_or(false, |args| args.is_present(name))
+        };
+        let verbosity = if is_present("quiet") {
+            Verbosity::Quiet
+        } else if is_present("verbose") {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        };
+        let color = gen_args
+            .value_of("color")
+            .or_else(|| subcmd_args.and_then(|args| args.value_of("color")))
+            .and_then(ColorChoice::from_name);
+        let global = CLArgs {
+            refresh: is_present("refresh"),
+            verbosity,
+            color,
+            ..CLArgs::default()
+        };
+        if gen_args.is_present("list") && !subcmd.is_empty() && subcmd != "list" {
+            Error::with_description(
+                "--list cannot be used with a subcommand nor a generator, use `cargo gen list`",
+                ErrorKind::ArgumentConflict,
+            ).exit();
+        }
+        match (subcmd, subcmd_args) {
+            ("list", Some(list_args)) => CLArgs {
+                command: Command::List,
+                all: list_args.is_present("all"),
+                long: list_args.is_present("long"),
+                tree: list_args.is_present("tree"),
+                message_format: match list_args.value_of("message-format") {
+                    Some("json") => MessageFormat::Json,
+                    _ => MessageFormat::Human,
+                },
+                category: list_args.value_of("category").map(|c| c.to_owned()),
+                tags: match list_args.values_of("tag") {
+                    Some(tags) => tags.map(|t| t.to_owned()).collect(),
+                    None => vec![],
+                },
+                ..global
+            },
+            ("info", Some(info_args)) => CLArgs {
+                command: Command::Info(info_args.value_of("GENERATOR").unwrap().to_owned()),
+                ..global
+            },
+            ("run", Some(run_args)) => CLArgs {
+                strict: run_args.is_present("strict"),
+                dry_run: run_args.is_present("dry-run"),
+                replay: run_args.is_present("replay"),
+                answers_file: run_args.value_of("answers-file").map(PathBuf::from),
+                args_file: run_args.value_of("args-file").map(PathBuf::from),
+                interactive: if run_args.is_present("interactive") {
+                    Interactive::Yes
+                } else {
+                    Interactive::No
+                },
+                gen_id: run_args.value_of("GENERATOR").map(|id| id.to_owned()),
+                gen_args: match run_args.values_of("GENERATOR") {
+                    Some(args) => args.skip(1).map(|arg| arg.to_owned()).collect(),
+                    None => vec![],
+                },
+                ..global
+            },
+            ("validate", Some(validate_args)) => CLArgs {
+                command: Command::Validate(ValidateArgs {
+                    manifest: validate_args.value_of("MANIFEST").map(PathBuf::from),
+                }),
+                ..global
+            },
+            ("undo", Some(undo_args)) => CLArgs {
+                command: Command::Undo(UndoArgs {
+                    force: undo_args.is_present("force"),
+                }),
+                ..global
+            },
+            ("search", Some(search_args)) => CLArgs {
+                all: search_args.is_present("all"),
+                command: Command::Search(search_args.value_of("TERM").unwrap().to_owned()),
+                ..global
+            },
+            ("install", Some(install_args)) => CLArgs {
+                command: Command::Install(PathBuf::from(install_args.value_of("PATH").unwrap())),
+                ..global
+            },
+            ("docs", Some(docs_args)) => CLArgs {
+                all: docs_args.is_present("all"),
+                command: Command::Docs(DocsArgs {
+                    output: docs_args.value_of("output").map(PathBuf::from),
+                    man_dir: docs_args.value_of("man").map(PathBuf::from),
+                }),
+                ..global
+            },
+            ("config", Some(config_args)) => CLArgs {
+                command: Command::Config(ConfigArgs {
+                    show: config_args.is_present("show"),
+                }),
+                ..global
+            },
+            ("completions", Some(completions_args)) => CLArgs {
+                command: Command::Completions(
+                    completions_args.value_of("SHELL").unwrap().to_owned(),
+                ),
+                ..global
+            },
+            ("complete-words", Some(complete_args)) => CLArgs {
+                command: Command::CompleteWords(match complete_args.values_of("WORDS") {
+                    Some(words) => words.map(|w| w.to_owned()).collect(),
+                    None => vec![],
+                }),
+                ..global
+            },
+            // `cargo gen GENERATOR [ARGS]...`
+            (subcmd, Some(subcmd_args)) => CLArgs {
+                gen_id: Some(subcmd.to_owned()),
+                gen_args: match subcmd_args.values_of("") {
                     Some(subcmd_args) => subcmd_args.map(|s| s.to_owned()).collect(),
                     None => vec![],
-                };
-                CLArgs {
-                    list: false,
-                    gen_id: Some(subcmd.to_owned()),
-                    gen_args: subcmd_args,
-                }
-            }
+                },
+                ..global
+            },
+            _ if gen_args.is_present("list") => CLArgs {
+                command: Command::List,
+                ..global
+            },
             _ => CLArgs {
-                list: gen_args.is_present("list"),
-                gen_id: None,
-                gen_args: vec![],
+                interactive: Interactive::IfTerminal,
+                ..global
             },
         }
     }
+
+    /// The help of `cargo gen`, as printed for `cargo gen --help`.
+    pub fn help() -> String {
+        match CLArgs::app().get_matches_from_safe(["cargo-gen", "gen", "--help"]) {
+            Err(err) => err.message,
+            Ok(_) => unreachable!("--help always stops the parsing"),
+        }
+    }
+
+    /// The identifier of the generator to run.
+    pub fn gen_id(&self) -> Option<&str> {
+        self.gen_id.as_deref()
+    }
+
+    /// The arguments for the generator to run.
+    pub fn gen_args(&self) -> &[String] {
+        &self.gen_args
+    }
 }
 
 #[cfg(test)]
 mod arg_parsing {
-    use super::CLArgs;
+    use super::{CLArgs, ColorChoice, Command, ConfigArgs, DocsArgs, Interactive, MessageFormat,
+                UndoArgs, ValidateArgs, Verbosity};
+    use std::path::PathBuf;
     use std::vec::IntoIter;
 
     fn args<'a>(suffix: &'a [&str]) -> IntoIter<&'a str> {
@@ -70,10 +639,229 @@ mod arg_parsing {
     // which is what we want in those cases. Only the successful examples are tested below.
 
     #[test]
-    fn it_sets_the_list_flag() {
-        assert_eq!(false, CLArgs::parse(args(&["app"])).list);
-        assert_eq!(true, CLArgs::parse(args(&["-l"])).list);
-        assert_eq!(true, CLArgs::parse(args(&["--list"])).list);
+    fn it_lists_with_the_list_subcommand() {
+        assert_eq!(Command::Run, CLArgs::parse(args(&["app"])).command);
+        assert_eq!(Command::List, CLArgs::parse(args(&["list"])).command);
+        assert_eq!(Command::List, CLArgs::parse(args(&["--list"])).command);
+        assert_eq!(Command::List, CLArgs::parse(args(&["-l"])).command);
+    }
+
+    #[test]
+    fn it_sets_the_all_flag() {
+        assert!(!CLArgs::parse(args(&["list"])).all);
+        assert!(CLArgs::parse(args(&["list", "--all"])).all);
+    }
+
+    #[test]
+    fn it_sets_the_long_flag() {
+        assert!(!CLArgs::parse(args(&["list"])).long);
+        assert!(CLArgs::parse(args(&["list", "--long"])).long);
+    }
+
+    #[test]
+    fn it_sets_the_tree_flag() {
+        assert!(!CLArgs::parse(args(&["list"])).tree);
+        assert!(CLArgs::parse(args(&["list", "--tree"])).tree);
+    }
+
+    #[test]
+    fn it_sets_the_message_format() {
+        assert_eq!(
+            MessageFormat::Human,
+            CLArgs::parse(args(&["list"])).message_format
+        );
+        assert_eq!(
+            MessageFormat::Json,
+            CLArgs::parse(args(&["list", "--message-format", "json"])).message_format
+        );
+        assert_eq!(
+            MessageFormat::Json,
+            CLArgs::parse(args(&["list", "--format", "json"])).message_format
+        );
+    }
+
+    #[test]
+    fn it_runs_a_generator_with_the_run_subcommand() {
+        let clargs = CLArgs::parse(args(&["run", "--strict", "list", "--force", "--", "blog"]));
+        assert!(clargs.strict);
+        assert_eq!(Some("list"), clargs.gen_id());
+        assert_eq!(&["--force", "--", "blog"], clargs.gen_args());
+        assert_eq!(Interactive::No, clargs.interactive);
+    }
+
+    #[test]
+    fn it_replays_the_recorded_answers() {
+        let clargs = CLArgs::parse(args(&["run", "--replay", "app", "--force"]));
+        assert!(clargs.replay);
+        assert_eq!(Some("app"), clargs.gen_id());
+        assert_eq!(&["--force".to_string()], clargs.gen_args());
+
+        let clargs = CLArgs::parse(args(&["run", "--answers-file", "answers.toml"]));
+        assert_eq!(Some(PathBuf::from("answers.toml")), clargs.answers_file);
+        assert_eq!(None, clargs.gen_id());
+        assert_eq!(Interactive::No, clargs.interactive);
+    }
+
+    #[test]
+    fn it_accepts_a_file_of_arguments() {
+        let clargs = CLArgs::parse(args(&["run", "--args-file", "args.json", "app", "blog"]));
+        assert_eq!(Some(PathBuf::from("args.json")), clargs.args_file);
+        assert_eq!(&["blog".to_string()], clargs.gen_args());
+    }
+
+    #[test]
+    fn it_sets_the_refresh_flag() {
+        assert!(!CLArgs::parse(args(&["list"])).refresh);
+        assert!(CLArgs::parse(args(&["list", "--refresh"])).refresh);
+        assert!(CLArgs::parse(args(&["--refresh", "list"])).refresh);
+    }
+
+    #[test]
+    fn it_sets_the_strict_flag() {
+        assert!(!CLArgs::parse(args(&["app"])).strict);
+        assert!(CLArgs::parse(args(&["run", "--strict", "app"])).strict);
+    }
+
+    #[test]
+    fn it_sets_the_dry_run_flag_of_run_only() {
+        assert!(CLArgs::parse(args(&["run", "--dry-run", "app"])).dry_run);
+        let clargs = CLArgs::parse(args(&["run", "app", "--dry-run"]));
+        assert!(!clargs.dry_run);
+        assert_eq!(vec!["--dry-run"], clargs.gen_args);
+    }
+
+    #[test]
+    fn it_is_interactive_without_a_subcommand() {
+        assert_eq!(Interactive::IfTerminal, CLArgs::parse(args(&[])).interactive);
+        assert_eq!(Interactive::IfTerminal, CLArgs::parse(args(&["--refresh"])).interactive);
+        assert_eq!(Interactive::Yes, CLArgs::parse(args(&["run", "-i"])).interactive);
+        assert_eq!(Interactive::No, CLArgs::parse(args(&["list"])).interactive);
+        assert_eq!(Interactive::No, CLArgs::parse(args(&["app"])).interactive);
+        assert!(CLArgs::help().contains("run"));
+    }
+
+    #[test]
+    fn it_sets_the_verbosity() {
+        assert_eq!(Verbosity::Normal, CLArgs::parse(args(&["app"])).verbosity);
+        assert_eq!(Verbosity::Quiet, CLArgs::parse(args(&["-q", "app"])).verbosity);
+        assert_eq!(
+            Verbosity::Verbose,
+            CLArgs::parse(args(&["list", "--verbose"])).verbosity
+        );
+    }
+
+    #[test]
+    fn it_sets_when_to_use_colours() {
+        assert_eq!(None, CLArgs::parse(args(&["app"])).color);
+        let clargs = CLArgs::parse(args(&["--color", "never", "search", "web"]));
+        assert_eq!(Some(ColorChoice::Never), clargs.color);
+        let clargs = CLArgs::parse(args(&["search", "web", "--color", "always"]));
+        assert_eq!(Some(ColorChoice::Always), clargs.color);
+        assert!(!ColorChoice::Never.enabled(true));
+        assert!(ColorChoice::Always.enabled(false));
+    }
+
+    #[test]
+    fn it_accepts_a_category_and_tags_to_filter_the_list() {
+        let clargs = CLArgs::parse(args(&["list", "--category", "testing"]));
+        assert_eq!(Some("testing".to_string()), clargs.category);
+        assert!(clargs.tags.is_empty());
+        assert_eq!(
+            vec!["web", "http"],
+            CLArgs::parse(args(&["list", "--tag", "web", "--tag", "http"])).tags
+        );
+    }
+
+    #[test]
+    fn it_accepts_a_path_to_install() {
+        assert_eq!(
+            Command::Install(PathBuf::from("../shared-gens")),
+            CLArgs::parse(args(&["install", "../shared-gens"])).command
+        );
+    }
+
+    #[test]
+    fn it_accepts_a_generator_to_show_info_about() {
+        let clargs = CLArgs::parse(args(&["info", "app"]));
+        assert_eq!(Command::Info("app".to_string()), clargs.command);
+        assert_eq!(None, clargs.gen_id);
+    }
+
+    #[test]
+    fn it_accepts_a_manifest_to_validate() {
+        assert_eq!(
+            Command::Validate(ValidateArgs::default()),
+            CLArgs::parse(args(&["validate"])).command
+        );
+        assert_eq!(
+            Command::Validate(ValidateArgs {
+                manifest: Some(PathBuf::from("cargo_generators.yaml")),
+            }),
+            CLArgs::parse(args(&["validate", "cargo_generators.yaml"])).command
+        );
+    }
+
+    #[test]
+    fn it_undoes_the_last_run() {
+        assert_eq!(
+            Command::Undo(UndoArgs { force: false }),
+            CLArgs::parse(args(&["undo"])).command
+        );
+        assert_eq!(
+            Command::Undo(UndoArgs { force: true }),
+            CLArgs::parse(args(&["undo", "--force"])).command
+        );
+    }
+
+    #[test]
+    fn it_accepts_a_term_to_search_for() {
+        let clargs = CLArgs::parse(args(&["search", "web.*"]));
+        assert_eq!(Command::Search("web.*".to_string()), clargs.command);
+        assert!(!clargs.all);
+        assert!(CLArgs::parse(args(&["search", "--all", "web"])).all);
+    }
+
+    #[test]
+    fn it_accepts_where_to_write_the_docs() {
+        assert_eq!(
+            Command::Docs(DocsArgs::default()),
+            CLArgs::parse(args(&["docs"])).command
+        );
+        assert_eq!(
+            Command::Docs(DocsArgs {
+                output: Some(PathBuf::from("GENERATORS.md")),
+                man_dir: Some(PathBuf::from("man")),
+            }),
+            CLArgs::parse(args(&["docs", "-o", "GENERATORS.md", "--man", "man"])).command
+        );
+    }
+
+    #[test]
+    fn it_shows_the_config() {
+        assert_eq!(
+            Command::Config(ConfigArgs::default()),
+            CLArgs::parse(args(&["config"])).command
+        );
+        assert_eq!(
+            Command::Config(ConfigArgs { show: true }),
+            CLArgs::parse(args(&["config", "--show"])).command
+        );
+    }
+
+    #[test]
+    fn it_accepts_a_shell_to_print_completions_for() {
+        assert_eq!(
+            Command::Completions("zsh".to_string()),
+            CLArgs::parse(args(&["completions", "zsh"])).command
+        );
+    }
+
+    #[test]
+    fn it_gathers_the_words_to_complete() {
+        assert_eq!(
+            Command::CompleteWords(vec!["app".to_string(), "--crate".to_string()]),
+            CLArgs::parse(args(&["complete-words", "--", "app", "--crate"])).command
+        );
     }
 
     #[test]
@@ -0,0 +1,303 @@
+//! Shell completions. The static part of a script comes from the clap `App` of `CLArgs`. The
+//! generators and their arguments are completed by calling `cargo-gen gen complete-words` back,
+//! which reads the discovery index. The scripts complete `cargo gen` as well as `cargo-gen`, the
+//! other cargo subcommands are left to the completion of cargo.
+extern crate clap;
+
+use catalog::GeneratorCatalog;
+use cmd_args::CLArgs;
+use failure::Error;
+use gen::GeneratorArg;
+use self::clap::Shell;
+
+const BIN_NAME: &str = "cargo-gen";
+
+const BASH_DYNAMIC: &str = r#"
+_cargo_gen_dynamic() {
+    _cargo-gen "$@"
+    (( COMP_CWORD < 2 )) && return
+    local static_reply=("${COMPREPLY[@]}")
+    local IFS=$'\n'
+    local dynamic_reply=($(cargo-gen gen complete-words -- "${COMP_WORDS[@]:2:COMP_CWORD-1}"))
+    COMPREPLY=("${static_reply[@]}" "${dynamic_reply[@]}")
+}
+complete -F _cargo_gen_dynamic -o bashdefault -o default cargo-gen
+
+_cargo_gen_cargo() {
+    if [[ ${COMP_WORDS[1]} == gen ]]; then
+        local COMP_WORDS=(cargo-gen "${COMP_WORDS[@]:1}")
+        _cargo_gen_dynamic "$@"
+    elif [[ -n $_cargo_gen_cargo_previous ]]; then
+        "$_cargo_gen_cargo_previous" "$@"
+    fi
+}
+if ! complete -p cargo &>/dev/null && declare -F _completion_loader &>/dev/null; then
+    _completion_loader cargo
+fi
+_cargo_gen_previous_spec=$(complete -p cargo 2>/dev/null)
+if [[ $_cargo_gen_previous_spec != *" _cargo_gen_cargo "* ]]; then
+    _cargo_gen_cargo_previous=$(sed -n 's/.*-F \([^ ]*\).*/\1/p' <<< "$_cargo_gen_previous_spec")
+fi
+complete -F _cargo_gen_cargo -o bashdefault -o default cargo
+"#;
+
+const ZSH_DYNAMIC: &str = r#"
+_cargo_gen_dynamic() {
+    local -a candidates
+    candidates=(${(f)"$(cargo-gen gen complete-words -- ${words[3,CURRENT]})"})
+    _cargo-gen "$@"
+    (( ${#candidates} )) && compadd -a candidates
+}
+compdef _cargo_gen_dynamic cargo-gen
+
+_cargo_gen_cargo() {
+    if [[ ${words[2]} == gen ]]; then
+        words[1]=cargo-gen
+        _cargo_gen_dynamic "$@"
+    else
+        ${_cargo_gen_cargo_previous:-_default} "$@"
+    fi
+}
+if [[ ${_comps[cargo]} != _cargo_gen_cargo ]]; then
+    _cargo_gen_cargo_previous=${_comps[cargo]}
+fi
+compdef _cargo_gen_cargo cargo
+"#;
+
+const FISH_DYNAMIC: &str = concat!(
+    "\ncomplete -c cargo-gen -n \"__fish_seen_subcommand_from gen\" -f -a ",
+    "\"(cargo-gen gen complete-words -- (commandline -opc)[3..-1] (commandline -ct))\"\n",
+);
+
+/// The fish completions add up, the ones of `cargo-gen` are repeated for `cargo` but for the
+/// top-level ones other than `gen`, cargo has its own.
+const FISH_COMMAND: &str = "complete -c cargo-gen ";
+const FISH_CARGO_COMMAND: &str = "complete -c cargo ";
+const FISH_TOP_LEVEL: &str = "-n \"__fish_use_subcommand\"";
+
+/// The completion script for `shell`, one of `bash`, `zsh` and `fish`.
+pub fn script(shell: &str) -> Result<String, Error> {
+    let (clap_shell, dynamic) = match shell {
+        "bash" => (Shell::Bash, BASH_DYNAMIC),
+        "zsh" => (Shell::Zsh, ZSH_DYNAMIC),
+        "fish" => (Shell::Fish, FISH_DYNAMIC),
+        _ => bail!("Completions for {} are not supported", shell),
+    };
+    let mut out = Vec::new();
+    CLArgs::app().gen_completions_to(BIN_NAME, clap_shell, &mut out);
+    let mut script = String::from_utf8(out)?;
+    if shell == "bash" {
+        // clap names the subcommands after `cargo__gen` but the command itself `cargo-gen`, so
+        // the subcommands are never completed. Name them all alike.
+        script = script
+            .replace("cmd=\"cargo-gen\"", "cmd=\"cargo__gen\"")
+            .replace("\n        cargo-gen)\n", "\n        cargo__gen)\n");
+    }
+    script.push_str(dynamic);
+    if shell == "fish" {
+        let cargo_script = script
+            .lines()
+            .filter(|line| !line.contains(FISH_TOP_LEVEL) || line.ends_with("-a \"gen\""))
+            .map(|line| format!("{}\n", line.replace(FISH_COMMAND, FISH_CARGO_COMMAND)))
+            .collect::<String>();
+        script.push_str(&cargo_script);
+    }
+    Ok(script)
+}
+
+/// Completes the last of `words`, the words after `cargo gen`, with what only discovery knows:
+/// generator ids, and the flags and possible values of the generator being called. The static
+/// flags and subcommands are left to the clap part of the script.
+pub fn candidates(catalog: &GeneratorCatalog, words: &[String]) -> Vec<String> {
+    let (current, mut before) = match words.split_last() {
+        Some((current, before)) => (current.as_str(), before),
+        None => ("", &[][..]),
+    };
+    // The options of `cargo gen` come before the subcommand or the generator.
+    before = match skip_options(before, &["--color"]) {
+        Some(rest) => rest,
+        None => return vec![],
+    };
+    // `cargo gen run [OPTIONS] GENERATOR` completes like `cargo gen GENERATOR`.
+    if before.first().map_or(false, |word| word == "run") {
+        before = match skip_options(&before[1..], &["--args-file", "--answers-file"]) {
+            Some(rest) => rest,
+            None => return vec![],
+        };
+        if before.is_empty() && current.starts_with('-') {
+            return vec![];
+        }
+    }
+    let generator_ids = || {
+        catalog
+            .iter()
+            .filter(|generator| !generator.is_hidden())
+            .flat_map(|generator| Some(&generator.name).into_iter().chain(generator.aliases()))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let mut candidates = match before.first().map(|word| word.as_str()) {
+        None if !current.starts_with('-') => generator_ids(),
+        Some("info") if before.len() == 1 => generator_ids(),
+        Some(id) => match catalog.get(id) {
+            None => vec![],
+            Some(generator) => {
+                let previous = before.last().map(|word| word.as_str()).unwrap_or("");
+                let is_previous = |arg: &&GeneratorArg| {
+                    arg.long
+                        .as_ref()
+                        .map_or(false, |long| previous == format!("--{}", long))
+                        || arg.short
+                            .as_ref()
+                            .map_or(false, |short| previous == format!("-{}", short))
+                };
+                let value_of = generator.args().iter().find(is_previous);
+                match value_of {
+                    Some(arg) if !arg.possible_values.is_empty() => arg.possible_values.clone(),
+                    _ if current.starts_with('-') => generator
+                        .args()
+                        .iter()
+                        .filter_map(|arg| arg.long.as_ref().map(|long| format!("--{}", long)))
+                        .collect(),
+                    // Positional arguments with possible values.
+                    _ => generator
+                        .args()
+                        .iter()
+                        .filter(|arg| arg.is_positional())
+                        .flat_map(|arg| arg.possible_values.clone())
+                        .collect(),
+                }
+            }
+        },
+        _ => vec![],
+    };
+    candidates.retain(|candidate| candidate.starts_with(current));
+    candidates
+}
+
+/// `words` without the options they start with, `None` if the word being completed is the value
+/// of the last one. `with_values` are the options that take the next word as their value.
+fn skip_options<'a>(mut words: &'a [String], with_values: &[&str]) -> Option<&'a [String]> {
+    while let Some((word, rest)) = words.split_first() {
+        if !word.starts_with('-') {
+            break;
+        }
+        words = rest;
+        if with_values.contains(&word.as_str()) {
+            if rest.is_empty() {
+                return None;
+            }
+            words = &rest[1..];
+        }
+    }
+    Some(words)
+}
+
+#[cfg(test)]
+mod completion_test {
+    extern crate cargo_gen_helpers;
+
+    use self::cargo_gen_helpers::create_file;
+    use self::cargo_gen_helpers::test_helpers::create_empty_crate;
+    use config::Config;
+    use gen::find_all;
+    use roots::isolate_home;
+    use super::*;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    fn catalog() -> GeneratorCatalog {
+        isolate_home();
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        create_file(
+            crate_dir.path().join("cargo_generators.yaml"),
+            "- name: web.app\n  factory: f\n  aliases: [web.application]\n  args:\n    \
+             - KIND:\n        possible_values: [api, site]\n    \
+             - crate-root:\n        long: crate-root\n    \
+             - db:\n        long: db\n        short: d\n        \
+             possible_values: [postgres, sqlite]\n\
+             - name: web.secret\n  factory: f\n  hidden: true",
+        ).unwrap();
+        GeneratorCatalog::from(find_all(crate_dir.path(), &Config::default()))
+    }
+
+    #[test]
+    fn it_completes_the_generator_ids() {
+        let catalog = catalog();
+        assert_eq!(
+            vec!["web.app", "web.application"],
+            candidates(&catalog, &words(&["web"]))
+        );
+        assert_eq!(
+            vec!["web.application"],
+            candidates(&catalog, &words(&["info", "web.appl"]))
+        );
+        assert_eq!(
+            vec!["web.app", "web.application"],
+            candidates(&catalog, &words(&["run", "--args-file", "args.json", "web"]))
+        );
+        assert!(candidates(&catalog, &words(&["--li"])).is_empty());
+    }
+
+    #[test]
+    fn it_skips_the_options_before_the_generator() {
+        let catalog = catalog();
+        assert_eq!(
+            vec!["web.app", "web.application"],
+            candidates(&catalog, &words(&["--refresh", "-q", "web"]))
+        );
+        assert_eq!(
+            vec!["web.app", "web.application"],
+            candidates(&catalog, &words(&["--color", "never", "run", "--dry-run", "web"]))
+        );
+        assert_eq!(
+            vec!["--crate-root", "--db"],
+            candidates(&catalog, &words(&["--color=always", "web.app", "--"]))
+        );
+        assert!(candidates(&catalog, &words(&["--color", ""])).is_empty());
+        assert!(candidates(&catalog, &words(&["run", "--args-file", ""])).is_empty());
+    }
+
+    #[test]
+    fn it_completes_the_flags_of_a_generator() {
+        assert_eq!(
+            vec!["--crate-root", "--db"],
+            candidates(&catalog(), &words(&["web.app", "--"]))
+        );
+        assert_eq!(
+            vec!["--crate-root", "--db"],
+            candidates(&catalog(), &words(&["run", "--strict", "web.app", "--"]))
+        );
+    }
+
+    #[test]
+    fn it_completes_the_possible_values_of_an_argument() {
+        let catalog = catalog();
+        assert_eq!(
+            vec!["sqlite"],
+            candidates(&catalog, &words(&["web.application", "-d", "s"]))
+        );
+        assert_eq!(vec!["api", "site"], candidates(&catalog, &words(&["web.app", ""])));
+    }
+
+    #[test]
+    fn it_renders_a_script_for_each_supported_shell() {
+        for shell in &["bash", "zsh", "fish"] {
+            assert!(script(shell).unwrap().contains("cargo-gen gen complete-words"));
+        }
+        assert!(script("powershell").is_err());
+    }
+
+    #[test]
+    fn it_completes_cargo_gen_as_well() {
+        assert!(script("bash").unwrap().contains("complete -F _cargo_gen_cargo"));
+        assert!(script("zsh").unwrap().contains("compdef _cargo_gen_cargo cargo"));
+        assert!(
+            script("fish")
+                .unwrap()
+                .contains("complete -c cargo -n \"__fish_seen_subcommand_from gen\"")
+        );
+    }
+}
@@ -0,0 +1,546 @@
+//! The config of cargo gen, merged from two optional TOML files. From the highest precedence to
+//! the lowest:
+//!
+//! 1. the arguments given on the command line, and for the arguments of the generators the
+//!    environment and the `--args-file` (see the `arg_values` module),
+//! 2. the project config, `.cargo-gen.toml` at the workspace root,
+//! 3. the user config, `cargo-gen/config.toml` in the XDG config directory (`~/.config` unless
+//!    `XDG_CONFIG_HOME` is set), or the file `CARGO_GEN_CONFIG` points to.
+//!
+//! Tables are merged key by key, any other value of the project config replaces the one of the
+//! user config, arrays included. Both files take the same keys:
+//!
+//! ```toml
+//! # Generators, by name or alias, and packages whose generators are left out.
+//! disabled = ["web.legacy"]
+//! disabled-packages = ["old-generators"]
+//! # Searched for generators after the `generator-paths` of the workspace manifest. Relative to
+//! # the config file, `~/` is the home directory.
+//! generator-paths = ["~/generators"]
+//! # What generators do with the files that exist already: overwrite, skip or fail.
+//! conflict = "skip"
+//! # Like --color: auto, always or never.
+//! color = "auto"
+//!
+//! # The `author` and `author-email` template variables unless `[variables]` sets them.
+//! [user]
+//! name = "Jane Doe"
+//! email = "jane@example.com"
+//!
+//! # Passed to every generator, see `cargo_gen_helpers::template_variable`.
+//! [variables]
+//! license = "MIT"
+//!
+//! # Default arguments of a generator, by argument name.
+//! [generators."web.app"]
+//! crate-root = "app"
+//! force = true
+//! ```
+extern crate dirs;
+extern crate toml;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use failure::{err_msg, Error};
+use cmd_args::ColorChoice;
+use arg_values::{apply, scalar, values_of};
+use gen::{Generator, ManifestError};
+use index::workspace_dir;
+
+pub const PROJECT_CONFIG: &str = ".cargo-gen.toml";
+/// The possible values of `conflict`.
+pub const CONFLICT_POLICIES: [&str; 3] = ["overwrite", "skip", "fail"];
+
+/// The user config and the project config merged.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// The files that exist, the user config first.
+    files: Vec<PathBuf>,
+    /// Every value that is not a table, by dotted key, with the file it comes from.
+    values: BTreeMap<String, (toml::Value, PathBuf)>,
+    settings: Settings,
+}
+
+/// A value the config gives to an argument of a generator.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArgDefault {
+    /// The name of the argument.
+    pub name: String,
+    /// The values of the argument, none for a flag that is set.
+    pub values: Vec<String>,
+}
+
+/// The keys of a config file, checked.
+#[derive(Clone, Debug, Default)]
+struct Settings {
+    disabled: Vec<String>,
+    disabled_packages: Vec<String>,
+    generator_paths: Vec<String>,
+    conflict: Option<String>,
+    color: Option<ColorChoice>,
+    variables: BTreeMap<String, String>,
+    generators: Vec<(String, toml::value::Table)>,
+}
+
+/// The user config file, `None` if there is no home directory to put it in.
+pub fn user_config_path() -> Option<PathBuf> {
+    match env::var_os("CARGO_GEN_CONFIG") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => xdg_config_dir(env::var_os("XDG_CONFIG_HOME"), dirs::home_dir())
+            .map(|dir| dir.join("cargo-gen").join("config.toml")),
+    }
+}
+
+/// `XDG_CONFIG_HOME`, or `~/.config` if it is unset or relative, on every platform.
+fn xdg_config_dir(xdg_config_home: Option<OsString>, home: Option<PathBuf>) -> Option<PathBuf> {
+    xdg_config_home
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| home.map(|home| home.join(".config")))
+}
+
+/// The project config file of the workspace `root_crate_path` belongs to.
+pub fn project_config_path(root_crate_path: &Path) -> PathBuf {
+    // Like the discovery index, the workspace root is found without asking cargo.
+    workspace_dir(root_crate_path).join(PROJECT_CONFIG)
+}
+
+impl Config {
+    /// Reads and merges the user config and the project config of the workspace
+    /// `root_crate_path` belongs to. Missing files count as empty.
+    pub fn load(root_crate_path: &Path) -> Result<Config, Error> {
+        let mut paths = user_config_path().into_iter().collect::<Vec<_>>();
+        paths.push(project_config_path(root_crate_path));
+        Config::from_files(&paths)
+    }
+
+    /// Merges the files in increasing order of precedence.
+    fn from_files(paths: &[PathBuf]) -> Result<Config, Error> {
+        let mut files = Vec::new();
+        let mut merged = toml::value::Table::new();
+        let mut values = BTreeMap::new();
+        for path in paths.iter().filter(|path| path.is_file()) {
+            let mut toml_str = String::new();
+            File::open(path)
+                .and_then(|mut file| file.read_to_string(&mut toml_str))
+                .map_err(|e| ManifestError::new(path, e))?;
+            let table = match toml_str.parse::<toml::Value>() {
+                Ok(toml::Value::Table(table)) => table,
+                Ok(_) => return Err(ManifestError::new(path, "The config is not a table").into()),
+                Err(e) => return Err(ManifestError::new(path, e).into()),
+            };
+            // Checked on its own so that the errors name the file at fault.
+            Settings::from_table(&table).map_err(|e| ManifestError::new(path, e))?;
+            merge(&mut merged, table, path, "", &mut values);
+            files.push(path.clone());
+        }
+        let mut settings = Settings::from_table(&merged)?;
+        let user = merged.get("user");
+        for &(key, variable) in &[("name", "author"), ("email", "author-email")] {
+            let value = user.and_then(|user| user.get(key)).and_then(scalar);
+            if let (false, Some(value)) = (settings.variables.contains_key(variable), value) {
+                settings.variables.insert(variable.to_owned(), value);
+            }
+        }
+        Ok(Config {
+            files,
+            values,
+            settings,
+        })
+    }
+
+    /// The config files that exist, the user config first.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// The file the value of `key`, e.g. `disabled`, comes from.
+    pub fn source(&self, key: &str) -> Option<&Path> {
+        self.values.get(key).map(|(_, path)| path.as_path())
+    }
+
+    /// The template variables, passed to every generator.
+    pub fn variables(&self) -> &BTreeMap<String, String> {
+        &self.settings.variables
+    }
+
+    /// What generators do with the files that exist already, one of `CONFLICT_POLICIES`.
+    pub fn conflict(&self) -> Option<&str> {
+        self.settings.conflict.as_deref()
+    }
+
+    pub fn color(&self) -> Option<ColorChoice> {
+        self.settings.color
+    }
+
+    /// The `generator-paths`, relative to the config file they come from.
+    pub fn generator_paths(&self) -> Vec<PathBuf> {
+        let dir = self
+            .source("generator-paths")
+            .and_then(|path| path.parent())
+            .unwrap_or_else(|| Path::new(""));
+        self.settings
+            .generator_paths
+            .iter()
+            .map(|path| match (path.strip_prefix("~/"), dirs::home_dir()) {
+                (Some(path), Some(home)) => home.join(path),
+                _ => dir.join(path),
+            })
+            .collect()
+    }
+
+    /// Whether the generator or its package is disabled.
+    pub fn is_disabled(&self, generator: &Generator) -> bool {
+        self.settings.disabled.iter().any(|id| generator.is_known_as(id))
+            || self.settings.disabled_packages.contains(&generator.package().name)
+    }
+
+    /// The default arguments of the generator, looked up by its name and by its aliases.
+    pub fn defaults(&self, generator: &Generator) -> Result<Vec<ArgDefault>, Error> {
+        let mut defaults = Vec::new();
+        for (id, args) in &self.settings.generators {
+            if !generator.is_known_as(id) {
+                continue;
+            }
+            for (name, value) in args {
+                let error = |message: String| -> Error {
+                    let key = format!("generators.{}.{}", key_segment(id), key_segment(name));
+                    let path = self.source(&key).unwrap_or_else(|| Path::new(PROJECT_CONFIG));
+                    ManifestError::new(path, message).into()
+                };
+                let arg = generator
+                    .args()
+                    .iter()
+                    .find(|arg| &arg.name == name)
+                    .ok_or_else(|| error(format!("{} has no argument {}", id, name)))?;
+                if arg.is_positional() {
+                    return Err(error(format!(
+                        "Argument {} of {} is positional, only options and flags have defaults",
+                        name, id
+                    )));
+                }
+                let value = values_of(arg, value)
+                    .map_err(|e| error(format!("{}.{} {}", id, name, e)))?;
+                defaults.retain(|default: &ArgDefault| &default.name != name);
+                defaults.extend(value);
+            }
+        }
+        Ok(defaults)
+    }
+
+    /// The arguments to run the generator with: `args` after the defaults of the arguments that
+    /// `args` leaves out.
+    pub fn apply(&self, generator: &Generator, args: &[String]) -> Result<Vec<String>, Error> {
+        apply(generator, args, &[self.defaults(generator)?])
+    }
+
+    /// Every value of the merged config with the file it comes from, a `key = value` line each.
+    pub fn show(&self) -> String {
+        let lines = self
+            .values
+            .iter()
+            .map(|(key, (value, path))| (format!("{} = {}", key, value), path))
+            .collect::<Vec<_>>();
+        let width = lines
+            .iter()
+            .map(|(line, _)| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut out = String::new();
+        for (line, path) in lines {
+            let padding = " ".repeat(width - line.chars().count());
+            out.push_str(&format!("{}{}  # {}\n", line, padding, path.display()));
+        }
+        out
+    }
+}
+
+impl Settings {
+    fn from_table(table: &toml::value::Table) -> Result<Settings, Error> {
+        let strings = |key: &str| -> Result<Vec<String>, Error> {
+            match table.get(key) {
+                None => Ok(vec![]),
+                Some(values) => values
+                    .as_array()
+                    .and_then(|values| {
+                        values
+                            .iter()
+                            .map(|value| value.as_str().map(|value| value.to_owned()))
+                            .collect()
+                    })
+                    .ok_or_else(|| format_err!("{} is not an array of strings", key)),
+            }
+        };
+        let one_of = |key: &str, possible_values: &[&str]| -> Result<Option<String>, Error> {
+            match table.get(key) {
+                None => Ok(None),
+                Some(value) => value
+                    .as_str()
+                    .filter(|value| possible_values.contains(value))
+                    .map(|value| Some(value.to_owned()))
+                    .ok_or_else(|| {
+                        format_err!("{} is not one of {}", key, possible_values.join(", "))
+                    }),
+            }
+        };
+        if let Some(user) = table.get("user") {
+            let user = user.as_table().ok_or_else(|| err_msg("user is not a table"))?;
+            for (key, value) in user {
+                if scalar(value).is_none() {
+                    bail!("user.{} is not a plain value", key);
+                }
+            }
+        }
+        let variables = match table.get("variables") {
+            None => BTreeMap::new(),
+            Some(variables) => variables
+                .as_table()
+                .ok_or_else(|| err_msg("variables is not a table"))?
+                .iter()
+                .map(|(name, value)| {
+                    scalar(value)
+                        .map(|value| (name.clone(), value))
+                        .ok_or_else(|| format_err!("Variable {} is not a plain value", name))
+                })
+                .collect::<Result<_, _>>()?,
+        };
+        let generators = match table.get("generators") {
+            None => vec![],
+            Some(generators) => generators
+                .as_table()
+                .ok_or_else(|| err_msg("generators is not a table"))?
+                .iter()
+                .map(|(id, args)| {
+                    args.as_table()
+                        .map(|args| (id.clone(), args.clone()))
+                        .ok_or_else(|| format_err!("generators.{} is not a table", id))
+                })
+                .collect::<Result<_, _>>()?,
+        };
+        Ok(Settings {
+            disabled: strings("disabled")?,
+            disabled_packages: strings("disabled-packages")?,
+            generator_paths: strings("generator-paths")?,
+            conflict: one_of("conflict", &CONFLICT_POLICIES)?,
+            color: one_of("color", &["auto", "always", "never"])?
+                .and_then(|color| ColorChoice::from_name(&color)),
+            variables,
+            generators,
+        })
+    }
+}
+
+/// Merges `from` into `into` and records the values of `from` as coming from `path`.
+fn merge(
+    into: &mut toml::value::Table,
+    from: toml::value::Table,
+    path: &Path,
+    prefix: &str,
+    values: &mut BTreeMap<String, (toml::Value, PathBuf)>,
+) {
+    for (key, value) in from {
+        let dotted = format!("{}{}", prefix, key_segment(&key));
+        match (into.get_mut(&key), value) {
+            (Some(toml::Value::Table(into)), toml::Value::Table(from)) => {
+                merge(into, from, path, &format!("{}.", dotted), values);
+            }
+            (_, value) => {
+                let nested = format!("{}.", dotted);
+                values.retain(|key, _| *key != dotted && !key.starts_with(&nested));
+                record(&dotted, &value, path, values);
+                into.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Records `value`, or every value in it if it is a table, as coming from `path`.
+fn record(
+    key: &str,
+    value: &toml::Value,
+    path: &Path,
+    values: &mut BTreeMap<String, (toml::Value, PathBuf)>,
+) {
+    match *value {
+        toml::Value::Table(ref table) => {
+            for (nested, value) in table {
+                record(&format!("{}.{}", key, key_segment(nested)), value, path, values);
+            }
+        }
+        ref value => {
+            values.insert(key.to_owned(), (value.clone(), path.to_path_buf()));
+        }
+    }
+}
+
+/// A key as written in a dotted TOML key, quoted unless it is a bare key.
+fn key_segment(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if bare {
+        key.to_owned()
+    } else {
+        format!("{:?}", key)
+    }
+}
+
+#[cfg(test)]
+mod config_test {
+    extern crate cargo_gen_helpers;
+    extern crate yaml_rust;
+
+    use self::cargo_gen_helpers::create_file;
+    use self::cargo_gen_helpers::test_helpers::create_empty_crate;
+    use self::yaml_rust::YamlLoader;
+    use super::*;
+
+    fn app() -> Generator {
+        Generator::try_from_yaml(
+            &YamlLoader::load_from_str(
+                "name: web.app\nfactory: f\naliases: [web.application]\nargs:\n  \
+                 - NAME:\n      index: 1\n  \
+                 - crate-root:\n      long: crate-root\n      short: c\n      \
+                 value_name: FOLDER\n  \
+                 - feature:\n      long: feature\n      takes_value: true\n      \
+                 multiple: true\n  \
+                 - force:\n      long: force",
+            ).unwrap()[0],
+        ).unwrap()
+    }
+
+    fn config(toml: &str) -> Config {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let path = crate_dir.path().join(PROJECT_CONFIG);
+        create_file(&path, toml).unwrap();
+        Config::from_files(&[path]).unwrap()
+    }
+
+    #[test]
+    fn it_finds_the_user_config_in_the_xdg_config_directory() {
+        let home = Some(PathBuf::from("/home/jane"));
+        assert_eq!(
+            Some(PathBuf::from("/xdg")),
+            xdg_config_dir(Some(OsString::from("/xdg")), home.clone())
+        );
+        assert_eq!(
+            Some(PathBuf::from("/home/jane/.config")),
+            xdg_config_dir(None, home.clone())
+        );
+        // The XDG base directory specification ignores relative paths.
+        assert_eq!(
+            Some(PathBuf::from("/home/jane/.config")),
+            xdg_config_dir(Some(OsString::from("xdg")), home)
+        );
+        assert_eq!(None, xdg_config_dir(None, None));
+    }
+
+    #[test]
+    fn it_finds_the_project_config_at_the_workspace_root() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let member_dir = crate_dir.path().join("member");
+        assert_eq!(member_dir.join(PROJECT_CONFIG), project_config_path(&member_dir));
+
+        create_file(crate_dir.path().join("Cargo.lock"), "").unwrap();
+        assert_eq!(
+            crate_dir.path().join(PROJECT_CONFIG),
+            project_config_path(&member_dir)
+        );
+    }
+
+    #[test]
+    fn it_merges_the_project_config_over_the_user_config() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let user = crate_dir.path().join("user.toml");
+        let project = crate_dir.path().join(PROJECT_CONFIG);
+        create_file(
+            &user,
+            "color = \"never\"\nconflict = \"skip\"\ngenerator-paths = [\"gens\"]\n\
+             [user]\nname = \"Jane\"\n[variables]\nlicense = \"MIT\"\nyear = 2018",
+        ).unwrap();
+        create_file(
+            &project,
+            "conflict = \"fail\"\n[variables]\nyear = 2019\n\
+             [generators.\"web.app\"]\ncrate-root = \"app\"",
+        ).unwrap();
+        let config = Config::from_files(&[user.clone(), project.clone()]).unwrap();
+        assert_eq!(&[user.clone(), project.clone()][..], config.files());
+        assert_eq!(Some(ColorChoice::Never), config.color());
+        assert_eq!(Some("fail"), config.conflict());
+        assert_eq!(vec![crate_dir.path().join("gens")], config.generator_paths());
+        assert_eq!(
+            vec![("author", "Jane"), ("license", "MIT"), ("year", "2019")],
+            config
+                .variables()
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(project.as_path()), config.source("variables.year"));
+        assert_eq!(Some(user.as_path()), config.source("variables.license"));
+
+        let show = config.show();
+        let line = |key_value: &str, path: &Path| {
+            let source = format!("  # {}", path.display());
+            show.lines()
+                .any(|line| line.starts_with(key_value) && line.ends_with(&source))
+        };
+        assert!(line("conflict = \"fail\"", &project), "{}", show);
+        assert!(line("generators.\"web.app\".crate-root = \"app\"", &project), "{}", show);
+        assert!(line("user.name = \"Jane\"", &user), "{}", show);
+    }
+
+    #[test]
+    fn it_names_the_file_at_fault() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let paths = [crate_dir.path().join("user.toml")];
+        create_file(&paths[0], "conflict = \"merge\"").unwrap();
+        let err = Config::from_files(&paths).unwrap_err();
+        assert_eq!(
+            Some(&paths[0]),
+            err.downcast_ref::<ManifestError>().map(|err| &err.path)
+        );
+    }
+
+    #[test]
+    fn it_disables_generators_and_packages() {
+        let app = app();
+        assert!(config("disabled = [\"web.application\"]").is_disabled(&app));
+        assert!(!config("disabled = [\"web.model\"]").is_disabled(&app));
+        // Generators parsed on their own belong to no package.
+        assert!(config("disabled-packages = [\"\"]").is_disabled(&app));
+    }
+
+    #[test]
+    fn it_applies_the_defaults_the_command_line_leaves_out() {
+        let config = config(
+            "[generators.\"web.app\"]\ncrate-root = \"app\"\nforce = true\n\
+             [generators.\"web.application\"]\nfeature = [\"a\", \"b\"]",
+        );
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            args(&["--crate-root", "app", "--feature", "a", "--feature", "b", "--force", "blog"]),
+            config.apply(&app(), &args(&["blog"])).unwrap()
+        );
+        assert_eq!(
+            args(&["--feature", "a", "--feature", "b", "-csrc", "--force", "blog"]),
+            config.apply(&app(), &args(&["-csrc", "--force", "blog"])).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_rejects_defaults_that_do_not_fit_the_arguments() {
+        for toml in &[
+            "[generators.\"web.app\"]\nNAME = \"blog\"",
+            "[generators.\"web.app\"]\nnothing = 1",
+            "[generators.\"web.app\"]\nforce = \"yes\"",
+        ] {
+            assert!(config(toml).defaults(&app()).is_err(), "{}", toml);
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! Renders the documentation of the generators for `cargo gen docs`, from the same metadata
+//! `list` and `info` show.
+use gen::Generator;
+use listing::{arg_help, usage};
+
+/// A Markdown catalog of the generators: an index table followed by a section per generator.
+pub fn markdown(generators: &[&Generator]) -> String {
+    let mut out = String::from("# Generators\n\n");
+    if generators.is_empty() {
+        out.push_str("This project has no generators.\n");
+        return out;
+    }
+    out.push_str("| Generator | About |\n| --- | --- |\n");
+    for generator in generators {
+        out.push_str(&format!(
+            "| [`{}`](#{}) | {} |\n",
+            generator.name,
+            anchor(&generator.name),
+            table_cell(generator.about().unwrap_or(""))
+        ));
+    }
+    for generator in generators {
+        out.push('\n');
+        out.push_str(&markdown_section(generator));
+    }
+    out
+}
+
+fn markdown_section(generator: &Generator) -> String {
+    let mut out = format!("## `{}`\n\n", generator.name);
+    if let Some(about) = generator.about() {
+        out.push_str(&format!("{}\n\n", about));
+    }
+    if let Some(replacement) = generator.deprecated() {
+        out.push_str(&format!("**Deprecated:** {}\n\n", replacement));
+    }
+
+    let package = generator.package();
+    let mut facts = vec![format!(
+        "Package: `{}`",
+        format!("{} {}", package.name, package.version).trim_end()
+    )];
+    if let Some(version) = generator.version() {
+        facts.push(format!("Version: {}", version));
+    }
+    if !generator.aliases().is_empty() {
+        facts.push(format!("Aliases: {}", code_list(generator.aliases())));
+    }
+    if let Some(category) = generator.category() {
+        facts.push(format!("Category: {}", category));
+    }
+    if !generator.tags().is_empty() {
+        facts.push(format!("Tags: {}", generator.tags().join(", ")));
+    }
+    if !generator.chain().is_empty() {
+        facts.push(format!("Chain: {}", code_list(generator.chain())));
+    }
+    if !generator.missing_features().is_empty() {
+        facts.push(format!(
+            "Requires the features: {}",
+            code_list(generator.missing_features())
+        ));
+    }
+    for fact in facts {
+        out.push_str(&format!("- {}\n", fact));
+    }
+
+    out.push_str(&format!("\n### Usage\n\n```sh\n{}\n```\n", synopsis(generator)));
+    if !generator.args().is_empty() {
+        out.push_str("\n### Arguments\n\n| Argument | Description |\n| --- | --- |\n");
+        for arg in generator.args() {
+            out.push_str(&format!(
+                "| `{}` | {} |\n",
+                usage(arg),
+                table_cell(&arg_help(arg))
+            ));
+        }
+    }
+    if !generator.examples().is_empty() {
+        out.push_str("\n### Examples\n\n```sh\n");
+        for example in generator.examples() {
+            out.push_str(&format!("{}\n", example));
+        }
+        out.push_str("```\n");
+    }
+    if let Some(notes) = generator.notes() {
+        out.push_str(&format!("\n### Notes\n\n{}\n", notes.trim_end()));
+    }
+    out
+}
+
+/// A man page in section 1 for the generator, to be saved as `man_page_name`.
+pub fn man_page(generator: &Generator) -> String {
+    let package = generator.package();
+    let mut out = format!(
+        ".TH \"{}\" 1 \"\" \"{}\" \"cargo gen\"\n",
+        roff(&format!("CARGO-GEN-{}", generator.name.to_uppercase())),
+        roff(format!("{} {}", package.name, package.version).trim_end())
+    );
+    out.push_str(".SH NAME\n");
+    match generator.about() {
+        Some(about) => out.push_str(&format!(
+            "cargo\\-gen\\-{} \\- {}\n",
+            roff(&generator.name),
+            roff(about)
+        )),
+        None => out.push_str(&format!("cargo\\-gen\\-{}\n", roff(&generator.name))),
+    }
+    out.push_str(&format!(".SH SYNOPSIS\n{}\n", roff(&synopsis(generator))));
+    if let Some(replacement) = generator.deprecated() {
+        out.push_str(&format!(".SH DEPRECATED\n{}\n", roff(replacement)));
+    }
+    if !generator.args().is_empty() {
+        out.push_str(".SH OPTIONS\n");
+        for arg in generator.args() {
+            out.push_str(&format!(
+                ".TP\n\\fB{}\\fR\n{}\n",
+                roff(&usage(arg)),
+                roff(&arg_help(arg))
+            ));
+        }
+    }
+    if !generator.examples().is_empty() {
+        out.push_str(".SH EXAMPLES\n.nf\n");
+        for example in generator.examples() {
+            out.push_str(&format!("{}\n", roff(example)));
+        }
+        out.push_str(".fi\n");
+    }
+    if let Some(notes) = generator.notes() {
+        out.push_str(&format!(".SH NOTES\n{}\n", roff(notes.trim_end())));
+    }
+    out.push_str(&format!(
+        ".SH SOURCE\nDefined in {} of the package {}.\n",
+        roff(&generator.manifest_path().to_string_lossy()),
+        roff(&package.id)
+    ));
+    out
+}
+
+/// The file name of the man page of the generator.
+pub fn man_page_name(generator: &Generator) -> String {
+    format!("cargo-gen-{}.1", generator.name)
+}
+
+/// E.g. `cargo gen web.app [OPTIONS] <NAME> [KIND]`.
+fn synopsis(generator: &Generator) -> String {
+    let mut synopsis = format!("cargo gen {}", generator.name);
+    let (positionals, options): (Vec<_>, Vec<_>) = generator
+        .args()
+        .iter()
+        .partition(|arg| arg.is_positional());
+    if !options.is_empty() {
+        synopsis.push_str(" [OPTIONS]");
+    }
+    let mut positionals = positionals;
+    positionals.sort_by_key(|arg| arg.index.unwrap_or(u64::MAX));
+    for arg in positionals {
+        if arg.required {
+            synopsis.push_str(&format!(" {}", usage(arg)));
+        } else {
+            synopsis.push_str(&format!(" [{}]", usage(arg)));
+        }
+    }
+    synopsis
+}
+
+/// The anchor GitHub gives to a heading, e.g. `webapp` for `` `web.app` ``.
+fn anchor(heading: &str) -> String {
+    heading
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+fn code_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("`{}`", item))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Escapes text for roff, also at the start of a line where `.` and `'` start requests.
+fn roff(text: &str) -> String {
+    text.replace('\\', "\\e")
+        .replace('-', "\\-")
+        .lines()
+        .map(|line| {
+            if line.starts_with('.') || line.starts_with('\'') {
+                format!("\\&{}", line)
+            } else {
+                line.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod docs_test {
+    extern crate yaml_rust;
+
+    use self::yaml_rust::YamlLoader;
+    use super::*;
+
+    fn generator(yaml: &str) -> Generator {
+        Generator::try_from_yaml(&YamlLoader::load_from_str(yaml).unwrap()[0]).unwrap()
+    }
+
+    fn app() -> Generator {
+        generator(
+            "name: web.app\nfactory: f\nabout: An app\nversion: \"0.1\"\nargs:\n  \
+             - NAME:\n      help: The name\n      required: true\n      index: 1\n  \
+             - crate-root:\n      help: The root | folder\n      long: crate-root\n      \
+             value_name: FOLDER\nexamples: [cargo gen web.app blog]\nnotes: Run it once",
+        )
+    }
+
+    #[test]
+    fn it_renders_an_index_and_a_section_per_generator() {
+        let out = markdown(&[&app()]);
+        assert!(out.starts_with("# Generators\n\n| Generator | About |\n| --- | --- |\n"));
+        assert!(out.contains("| [`web.app`](#webapp) | An app |\n"));
+        assert!(out.contains("## `web.app`\n\nAn app\n\n- Package: ``\n- Version: 0.1\n"));
+        assert!(out.contains("```sh\ncargo gen web.app [OPTIONS] <NAME>\n```\n"));
+        assert!(out.contains("| `--crate-root <FOLDER>` | The root \\| folder |\n"));
+        assert!(out.contains("### Examples\n\n```sh\ncargo gen web.app blog\n```\n"));
+        assert!(out.ends_with("### Notes\n\nRun it once\n"));
+    }
+
+    #[test]
+    fn it_says_so_if_there_are_no_generators() {
+        assert_eq!(
+            "# Generators\n\nThis project has no generators.\n",
+            markdown(&[])
+        );
+    }
+
+    #[test]
+    fn it_renders_a_man_page() {
+        let app = app();
+        let page = man_page(&app);
+        assert!(page.starts_with(".TH \"CARGO\\-GEN\\-WEB.APP\" 1"));
+        assert!(page.contains(".SH NAME\ncargo\\-gen\\-web.app \\- An app\n"));
+        assert!(page.contains(".TP\n\\fB\\-\\-crate\\-root <FOLDER>\\fR\n"));
+        assert_eq!("cargo-gen-web.app.1", man_page_name(&app));
+    }
+
+    #[test]
+    fn it_escapes_roff_requests() {
+        assert_eq!("\\&.SH\nC:\\eTemp", roff(".SH\nC:\\Temp"));
+    }
+}
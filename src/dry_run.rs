@@ -0,0 +1,288 @@
+//! `cargo gen run --dry-run`: the generator runs against a staging directory instead of the
+//! project, see `cargo_gen_helpers::is_dry_run`, and the changes it staged are printed as a
+//! unified diff.
+extern crate diff;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use failure::Error;
+use tempdir::TempDir;
+
+/// The files the generator writes, under their absolute path without the root.
+const STAGED_FILES_DIR: &str = "files";
+/// The files the generator removes, as empty files.
+const REMOVED_FILES_DIR: &str = "removed";
+/// The lines of context around the changes.
+const CONTEXT: usize = 3;
+
+const BOLD: &str = "\x1b[1m";
+const CYAN: &str = "\x1b[36m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+/// The staging directory of a dry run, removed when dropped.
+#[derive(Debug)]
+pub struct Staging {
+    dir: TempDir,
+}
+
+/// A file the generator would create, change or remove.
+#[derive(Debug, PartialEq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    /// `None` if the file does not exist.
+    pub before: Option<Vec<u8>>,
+    /// `None` if the generator removes the file.
+    pub after: Option<Vec<u8>>,
+}
+
+impl Staging {
+    /// A new directory with a random name, that only the current user can access on Unix.
+    pub fn create() -> Result<Staging, Error> {
+        let dir = TempDir::new("cargo-gen-dry-run")
+            .map_err(|e| format_err!("The staging directory could not be created: {}", e))?;
+        Ok(Staging { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// The changes the generator staged, by path. The files it wrote as they were are left out.
+    pub fn changes(&self) -> Result<Vec<FileChange>, Error> {
+        let mut changes = vec![];
+        for path in staged_paths(&self.dir().join(STAGED_FILES_DIR))? {
+            let after = fs::read(self.dir().join(STAGED_FILES_DIR).join(&path))?;
+            let path = absolute(&path);
+            let before = fs::read(&path).ok();
+            if before.as_ref() != Some(&after) {
+                changes.push(FileChange {
+                    path,
+                    before,
+                    after: Some(after),
+                });
+            }
+        }
+        for path in staged_paths(&self.dir().join(REMOVED_FILES_DIR))? {
+            let path = absolute(&path);
+            if let Ok(before) = fs::read(&path) {
+                changes.push(FileChange {
+                    path,
+                    before: Some(before),
+                    after: None,
+                });
+            }
+        }
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(changes)
+    }
+}
+
+/// The files under `dir`, relative to it.
+fn staged_paths(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(current) = dirs.pop() {
+        if !current.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                paths.push(path.strip_prefix(dir)?.to_path_buf());
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// The path a staged path stands for.
+fn absolute(staged: &Path) -> PathBuf {
+    Path::new("/").join(staged)
+}
+
+/// The change as a unified diff, with `label` as the path of the file. `color` wraps the lines in
+/// terminal escape codes.
+pub fn unified_diff(change: &FileChange, label: &Path, color: bool) -> String {
+    let paint = |code: &str, line: String| {
+        if color {
+            format!("{}{}{}\n", code, line, RESET)
+        } else {
+            format!("{}\n", line)
+        }
+    };
+    let label = label.display();
+    let (old_label, new_label) = match (&change.before, &change.after) {
+        (None, _) => ("/dev/null".to_owned(), format!("b/{}", label)),
+        (_, None) => (format!("a/{}", label), "/dev/null".to_owned()),
+        _ => (format!("a/{}", label), format!("b/{}", label)),
+    };
+    let text = |content: &Option<Vec<u8>>| match *content {
+        Some(ref content) => String::from_utf8(content.clone()).ok(),
+        None => Some(String::new()),
+    };
+    let (before, after) = match (text(&change.before), text(&change.after)) {
+        (Some(before), Some(after)) => (before, after),
+        _ => {
+            let line = format!("Binary files {} and {} differ", old_label, new_label);
+            return paint(BOLD, line);
+        }
+    };
+
+    let mut out = paint(BOLD, format!("--- {}", old_label));
+    out.push_str(&paint(BOLD, format!("+++ {}", new_label)));
+    // The lines keep their newline so that a last line without one differs from the same line
+    // with one, as it does for `diff`.
+    let (before, after) = (
+        before.split_inclusive('\n').collect::<Vec<_>>(),
+        after.split_inclusive('\n').collect::<Vec<_>>(),
+    );
+    let lines = diff::slice(&before, &after);
+    for (start, end) in hunks(&lines) {
+        let old_before = count_old(&lines[..start]);
+        let new_before = count_new(&lines[..start]);
+        let (old_len, new_len) = (count_old(&lines[start..end]), count_new(&lines[start..end]));
+        // An empty range starts at the line before it.
+        let old_start = if old_len == 0 { old_before } else { old_before + 1 };
+        let new_start = if new_len == 0 { new_before } else { new_before + 1 };
+        let header = format!("@@ -{},{} +{},{} @@", old_start, old_len, new_start, new_len);
+        out.push_str(&paint(CYAN, header));
+        for line in &lines[start..end] {
+            let (shown, line) = match *line {
+                diff::Result::Left(line) => {
+                    (paint(RED, format!("-{}", without_newline(line))), line)
+                }
+                diff::Result::Right(line) => {
+                    (paint(GREEN, format!("+{}", without_newline(line))), line)
+                }
+                diff::Result::Both(line, _) => (format!(" {}\n", without_newline(line)), line),
+            };
+            out.push_str(&shown);
+            if !line.ends_with('\n') {
+                out.push_str("\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+fn without_newline(line: &str) -> &str {
+    line.strip_suffix('\n').unwrap_or(line)
+}
+
+/// The ranges of `lines` shown in the hunks: the changes with their context, merged when they
+/// overlap.
+fn hunks(lines: &[diff::Result<&&str>]) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        if let diff::Result::Both(..) = *line {
+            continue;
+        }
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + 1 + CONTEXT).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    hunks
+}
+
+fn count_old(lines: &[diff::Result<&&str>]) -> usize {
+    lines
+        .iter()
+        .filter(|line| !matches!(**line, diff::Result::Right(_)))
+        .count()
+}
+
+fn count_new(lines: &[diff::Result<&&str>]) -> usize {
+    lines
+        .iter()
+        .filter(|line| !matches!(**line, diff::Result::Left(_)))
+        .count()
+}
+
+#[cfg(test)]
+mod dry_run_test {
+    use super::*;
+
+    fn change(before: Option<&str>, after: Option<&str>) -> FileChange {
+        FileChange {
+            path: PathBuf::from("/project/src/lib.rs"),
+            before: before.map(|before| before.as_bytes().to_vec()),
+            after: after.map(|after| after.as_bytes().to_vec()),
+        }
+    }
+
+    #[test]
+    fn it_prints_the_changes_with_their_context() {
+        let before = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+        let after = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        assert_eq!(
+            "--- a/src/lib.rs\n\
+             +++ b/src/lib.rs\n\
+             @@ -1,5 +1,5 @@\n \
+             a\n\
+             -b\n\
+             +B\n \
+             c\n \
+             d\n \
+             e\n\
+             @@ -10,3 +10,4 @@\n \
+             j\n \
+             k\n \
+             l\n\
+             +m\n",
+            unified_diff(&change(Some(before), Some(after)), Path::new("src/lib.rs"), false)
+        );
+    }
+
+    #[test]
+    fn it_diffs_created_and_removed_files_against_nothing() {
+        let label = Path::new("src/app.rs");
+        assert_eq!(
+            "--- /dev/null\n+++ b/src/app.rs\n@@ -0,0 +1,2 @@\n+mod a;\n+mod b;\n",
+            unified_diff(&change(None, Some("mod a;\nmod b;\n")), label, false)
+        );
+        assert_eq!(
+            "--- a/src/app.rs\n+++ /dev/null\n@@ -1,1 +0,0 @@\n-mod a;\n",
+            unified_diff(&change(Some("mod a;\n"), None), label, false)
+        );
+        assert_eq!(
+            "\x1b[1m--- /dev/null\x1b[0m\n\
+             \x1b[1m+++ b/src/app.rs\x1b[0m\n\
+             \x1b[36m@@ -0,0 +1,1 @@\x1b[0m\n\
+             \x1b[32m+mod a;\x1b[0m\n\
+             \\ No newline at end of file\n",
+            unified_diff(&change(None, Some("mod a;")), label, true)
+        );
+    }
+
+    #[test]
+    fn it_marks_the_last_lines_without_a_newline() {
+        let label = Path::new("src/lib.rs");
+        assert_eq!(
+            "--- a/src/lib.rs\n\
+             +++ b/src/lib.rs\n\
+             @@ -1,1 +1,2 @@\n\
+             -mod a;\n\
+             \\ No newline at end of file\n\
+             +mod a;\n\
+             +mod b;\n",
+            unified_diff(&change(Some("mod a;"), Some("mod a;\nmod b;\n")), label, false)
+        );
+        assert_eq!(
+            "--- a/src/lib.rs\n\
+             +++ b/src/lib.rs\n\
+             @@ -1,2 +1,2 @@\n \
+             mod a;\n\
+             -mod b;\n\
+             +mod b;\n\
+             \\ No newline at end of file\n",
+            unified_diff(&change(Some("mod a;\nmod b;\n"), Some("mod a;\nmod b;")), label, false)
+        );
+    }
+}
@@ -1,23 +1,142 @@
 extern crate cargo_metadata;
 extern crate yaml_rust;
 
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::fs::File;
 use std::ffi::OsStr;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use self::yaml_rust::{Yaml, YamlLoader};
-use self::cargo_metadata::metadata_deps;
-use failure::{err_msg, Error, SyncFailure};
+use self::yaml_rust::yaml::Hash as YamlHash;
+use self::cargo_metadata::{Metadata, MetadataCommand, PackageId};
+use failure::{err_msg, Error, Fail, SyncFailure};
+use config::Config;
+use git::LOCKFILE;
+use index::Index;
+use roots::{extra_package_roots, generator_pack_roots, local_package};
+
+const GENERATORS_YAML: &str = "cargo_generators.yaml";
 
 #[derive(Debug)]
 pub struct Generator {
     pub name: String,
     factory: String,
+    about: Option<String>,
+    version: Option<String>,
+    args: Vec<GeneratorArg>,
+    requires_features: Vec<String>,
+    /// The `requires_features` that are not enabled for the package of the generator.
+    missing_features: Vec<String>,
+    hidden: bool,
+    deprecated: Option<String>,
+    aliases: Vec<String>,
+    category: Option<String>,
+    tags: Vec<String>,
+    chain: Vec<String>,
+    examples: Vec<String>,
+    notes: Option<String>,
+    package: PackageInfo,
+    manifest_path: PathBuf,
+}
+
+/// A command line argument of a generator, as declared in its clap YAML. Only the settings worth
+/// showing are kept.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GeneratorArg {
+    pub name: String,
+    pub help: Option<String>,
+    pub long: Option<String>,
+    pub short: Option<String>,
+    pub value_name: Option<String>,
+    pub default_value: Option<String>,
+    pub takes_value: bool,
+    pub required: bool,
+    pub multiple: bool,
+    pub index: Option<u64>,
+    pub possible_values: Vec<String>,
+    /// The question `cargo gen` asks for the argument in interactive mode. The generators remove it
+    /// with `cargo_gen_helpers::clap_yaml` before giving their YAML to clap.
+    pub prompt: Option<String>,
+}
+
+impl GeneratorArg {
+    fn try_from_yaml(yaml: &Yaml, generator_name: &str) -> Result<GeneratorArg, Error> {
+        let (name, settings) = yaml
+            .as_hash()
+            .filter(|arg_hash| arg_hash.len() == 1)
+            .and_then(|arg_hash| arg_hash.iter().next())
+            .and_then(|(name, settings)| name.as_str().map(|name| (name, settings)))
+            .ok_or_else(|| {
+                format_err!(
+                    "An argument of generator {} is not a hash with the argument name as its \
+                     only key",
+                    generator_name
+                )
+            })?;
+        let empty = YamlHash::new();
+        let settings = match *settings {
+            Yaml::Null => &empty,
+            ref settings => settings.as_hash().ok_or_else(|| {
+                format_err!(
+                    "The settings of argument {} of generator {} are not a hash",
+                    name,
+                    generator_name
+                )
+            })?,
+        };
+        let context = format!("argument {} of generator {}", name, generator_name);
+        let string = |key: &str| optional_string(settings, key, &context);
+        let flag = |key: &str| match settings.get(&Yaml::from_str(key)) {
+            None => Ok(false),
+            Some(value) => value
+                .as_bool()
+                .ok_or_else(|| format_err!("The {} of {} is not a boolean", key, context)),
+        };
+        let index = match settings.get(&Yaml::from_str("index")) {
+            None => None,
+            Some(index) => Some(
+                index
+                    .as_i64()
+                    .filter(|index| *index > 0)
+                    .ok_or_else(|| format_err!("The index of {} is not a position", context))?
+                    as u64,
+            ),
+        };
+        Ok(GeneratorArg {
+            name: name.to_owned(),
+            help: string("help")?,
+            long: string("long")?,
+            short: string("short")?,
+            value_name: string("value_name")?,
+            default_value: string("default_value")?,
+            takes_value: flag("takes_value")?,
+            required: flag("required")?,
+            multiple: flag("multiple")?,
+            index,
+            possible_values: optional_strings(settings, "possible_values", &context)?,
+            prompt: string("prompt")?,
+        })
+    }
+
+    /// Whether the argument is positional rather than a flag or an option.
+    pub fn is_positional(&self) -> bool {
+        self.long.is_none() && self.short.is_none()
+    }
+
+    /// Whether the argument has a value, as opposed to a flag that is only present or not.
+    pub fn takes_value(&self) -> bool {
+        self.is_positional()
+            || self.takes_value
+            || self.value_name.is_some()
+            || self.default_value.is_some()
+            || !self.possible_values.is_empty()
+    }
 }
 
 impl Generator {
     // FIXME: more context in error messages.
-    fn try_from_yaml(yaml_doc: &Yaml) -> Result<Generator, Error> {
+    pub(crate) fn try_from_yaml(yaml_doc: &Yaml) -> Result<Generator, Error> {
         let gen_hash = yaml_doc
             .as_hash()
             .ok_or_else(|| err_msg("A generator config is not a hash"))?;
@@ -33,76 +152,657 @@ impl Generator {
             .as_str()
             .ok_or_else(|| format_err!("A factory of generator {} is not a string", name))?
             .to_owned();
-        Ok(Generator { name, factory })
+        let context = format!("generator {}", name);
+        let about = optional_string(gen_hash, "about", &context)?;
+        // Unquoted versions such as 0.1 are numbers in YAML.
+        let version = match gen_hash.get(&Yaml::from_str("version")) {
+            None => None,
+            Some(&Yaml::String(ref version)) | Some(&Yaml::Real(ref version)) => {
+                Some(version.clone())
+            }
+            Some(&Yaml::Integer(version)) => Some(version.to_string()),
+            Some(_) => bail!("The version of generator {} is not a string", name),
+        };
+        let args = match gen_hash.get(&Yaml::from_str("args")) {
+            None => vec![],
+            Some(args) => args
+                .as_vec()
+                .ok_or_else(|| format_err!("The args of generator {} is not an array", name))?
+                .iter()
+                .map(|arg| GeneratorArg::try_from_yaml(arg, &name))
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        let requires_features = optional_strings(gen_hash, "requires-features", &context)?;
+        let hidden = match gen_hash.get(&Yaml::from_str("hidden")) {
+            None => false,
+            Some(hidden) => hidden
+                .as_bool()
+                .ok_or_else(|| format_err!("The hidden of generator {} is not a boolean", name))?,
+        };
+        let deprecated = optional_string(gen_hash, "deprecated", &context)?;
+        let aliases = optional_strings(gen_hash, "aliases", &context)?;
+        let category = optional_string(gen_hash, "category", &context)?;
+        let tags = optional_strings(gen_hash, "tags", &context)?;
+        let chain = optional_strings(gen_hash, "chain", &context)?;
+        let examples = optional_strings(gen_hash, "examples", &context)?;
+        let notes = optional_string(gen_hash, "notes", &context)?;
+        Ok(Generator {
+            name,
+            factory,
+            about,
+            version,
+            args,
+            requires_features,
+            missing_features: vec![],
+            hidden,
+            deprecated,
+            aliases,
+            category,
+            tags,
+            chain,
+            examples,
+            notes,
+            package: PackageInfo::default(),
+            manifest_path: PathBuf::new(),
+        })
+    }
+
+    pub(crate) fn set_package(&mut self, package: PackageInfo, manifest_path: PathBuf) {
+        self.package = package;
+        self.manifest_path = manifest_path;
+    }
+
+    /// Whether the generator goes by `id`, either by its name or by one of its aliases.
+    pub fn is_known_as(&self, id: &str) -> bool {
+        self.name == id || self.aliases.iter().any(|alias| alias == id)
+    }
+
+    /// Hidden generators are not listed but can still be run.
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// What to use instead, if the generator is deprecated.
+    pub fn deprecated(&self) -> Option<&str> {
+        self.deprecated.as_deref()
+    }
+
+    /// Other identifiers the generator can be run by, usually its former names.
+    pub fn aliases(&self) -> &[String] {
+        &self.aliases
+    }
+
+    /// The group the generator is listed under.
+    pub fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+
+    /// Keywords to filter the generators by.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// The generators the manifest declares to go together with this one, in order.
+    pub fn chain(&self) -> &[String] {
+        &self.chain
+    }
+
+    /// Example invocations of the generator.
+    pub fn examples(&self) -> &[String] {
+        &self.examples
+    }
+
+    /// Free-form notes of the generator author.
+    pub fn notes(&self) -> Option<&str> {
+        self.notes.as_deref()
+    }
+
+    /// Whether the generator is in `category`, if given, and has all the `tags`.
+    pub fn matches(&self, category: Option<&str>, tags: &[String]) -> bool {
+        category.map_or(true, |category| self.category() == Some(category))
+            && tags.iter().all(|tag| self.tags.contains(tag))
+    }
+
+    /// The one line description of the generator.
+    pub fn about(&self) -> Option<&str> {
+        self.about.as_deref()
+    }
+
+    /// The version of the generator, which is not necessarily the version of its package.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
     }
+
+    /// The command line arguments the generator accepts.
+    pub fn args(&self) -> &[GeneratorArg] {
+        &self.args
+    }
+
+    /// The package that defines the generator.
+    pub fn package(&self) -> &PackageInfo {
+        &self.package
+    }
+
+    /// The id of the package that defines the generator, as reported by `cargo metadata`.
+    pub fn package_id(&self) -> &str {
+        &self.package.id
+    }
+
+    /// The path to the type that builds the generator from the command line arguments.
+    pub fn factory(&self) -> &str {
+        &self.factory
+    }
+
+    /// The `cargo_generators.yaml` that defines the generator.
+    pub fn manifest_path(&self) -> &Path {
+        &self.manifest_path
+    }
+
+    /// The cargo features the generator requires but are not enabled for its package.
+    pub fn missing_features(&self) -> &[String] {
+        &self.missing_features
+    }
+
+    /// Whether all the cargo features the generator requires are enabled.
+    pub fn is_available(&self) -> bool {
+        self.missing_features.is_empty()
+    }
+
+    /// Explains why the generator is not available, if it is not.
+    pub fn unavailable_reason(&self) -> Option<String> {
+        if self.is_available() {
+            return None;
+        }
+        Some(format!(
+            "Generator {} requires the cargo feature(s) {} of the package at {} to be enabled \
+             but {} not",
+            self.name,
+            self.requires_features.join(", "),
+            self.manifest_path
+                .parent()
+                .unwrap_or(&self.manifest_path)
+                .display(),
+            if self.missing_features.len() == 1 {
+                format!("{} is", self.missing_features[0])
+            } else {
+                format!("{} are", self.missing_features.join(", "))
+            }
+        ))
+    }
+}
+
+/// An optional string of a generator config. `context` names what the config belongs to, e.g.
+/// `generator app`, for the error messages.
+fn optional_string(hash: &YamlHash, key: &str, context: &str) -> Result<Option<String>, Error> {
+    match hash.get(&Yaml::from_str(key)) {
+        None => Ok(None),
+        Some(value) => value
+            .as_str()
+            .map(|value| Some(value.to_owned()))
+            .ok_or_else(|| format_err!("The {} of {} is not a string", key, context)),
+    }
+}
+
+/// An optional array of strings of a generator config. `context` is as for `optional_string`.
+fn optional_strings(hash: &YamlHash, key: &str, context: &str) -> Result<Vec<String>, Error> {
+    match hash.get(&Yaml::from_str(key)) {
+        None => Ok(vec![]),
+        Some(values) => values
+            .as_vec()
+            .ok_or_else(|| format_err!("The {} of {} is not an array", key, context))?
+            .iter()
+            .map(|value| {
+                value.as_str().map(|v| v.to_owned()).ok_or_else(|| {
+                    format_err!("An entry of {} of {} is not a string", key, context)
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Discovery could not run at all, e.g. because the dependency graph could not be resolved.
+#[derive(Debug)]
+pub struct DiscoveryError {
+    message: String,
+}
+
+impl From<Error> for DiscoveryError {
+    fn from(err: Error) -> DiscoveryError {
+        DiscoveryError {
+            message: err.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for DiscoveryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Could not discover the generators: {}", self.message)
+    }
+}
+
+impl Fail for DiscoveryError {}
+
+/// A generator manifest, or one of the generators it defines, is invalid. The other manifests are
+/// not affected.
+#[derive(Clone, Debug)]
+pub struct ManifestError {
+    pub path: PathBuf,
+    message: String,
 }
 
-pub fn find_all<P>(root_crate_path: P) -> Vec<Result<Generator, Error>>
+impl ManifestError {
+    pub(crate) fn new<M: fmt::Display>(path: &Path, message: M) -> ManifestError {
+        ManifestError {
+            path: path.to_path_buf(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl Fail for ManifestError {}
+
+/// What a generator knows about its package.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PackageInfo {
+    /// The package id as reported by `cargo metadata`.
+    pub id: String,
+    pub name: String,
+    /// Empty for the packages outside the dependency graph that have no valid manifest.
+    pub version: String,
+    /// The names of the packages from the root crate (or a workspace member) down to this one.
+    /// Empty for the packages outside the dependency graph.
+    pub dependency_path: Vec<String>,
+}
+
+/// A `cargo_generators.yaml` together with what discovery knows about its package.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorManifest {
+    pub path: PathBuf,
+    /// The package the manifest belongs to.
+    pub package: PackageInfo,
+    /// The features enabled for the package in the resolved dependency graph. Packages that are
+    /// not part of the graph (e.g. from the workspace generator paths) have `None` and their
+    /// generators' feature requirements are not checked.
+    pub features: Option<Vec<String>>,
+}
+
+/// The generators available to the crate at `root_crate_path`, with the generator paths of
+/// `config`.
+pub fn find_all<P>(root_crate_path: P, config: &Config) -> Vec<Result<Generator, Error>>
 where
     P: AsRef<Path> + AsRef<OsStr>,
 {
     // Find roots of all crates.
     match list_dep_root_dirs(root_crate_path) {
-        Err(e) => vec![Err(e)],
-        Ok(dep_root_dirs) => {
+        Err(e) => vec![Err(DiscoveryError::from(e).into())],
+        Ok((workspace_root, dep_roots)) => {
             // Find all cargo_generators.yaml's in all roots.
-            let cg_yamls = find_yaml_files_in_dirs(&dep_root_dirs);
-            // Parse each yaml
-            let parsed_yamls = parse_yamls(cg_yamls);
-            // Parse generators
-            parse_generators(parsed_yamls)
+            let cg_yamls = find_yaml_files_in_dirs(&dep_roots);
+            let pack_roots = generator_pack_roots(&workspace_root, false);
+            find_generators(&workspace_root, cg_yamls, pack_roots, config)
         }
     }
 }
 
-fn list_dep_root_dirs<P>(root_crate_path: P) -> Result<Vec<PathBuf>, Error>
+/// Same as `find_all` but reuses the discovery index of the previous run while it is fresh. The
+/// index is rebuilt (and the dependency graph resolved again) if `refresh` is set, which also moves
+/// the generator packs that follow a branch to its latest commit.
+pub fn find_all_cached<P>(
+    root_crate_path: P,
+    refresh: bool,
+    config: &Config,
+) -> Vec<Result<Generator, Error>>
+where
+    P: AsRef<Path> + AsRef<OsStr>,
+{
+    let root_crate_path = Path::new(&root_crate_path);
+    let cached = if refresh {
+        None
+    } else {
+        Index::load_fresh(root_crate_path)
+    };
+    let (index, pack_errors) = match cached {
+        Some(index) => {
+            debug!("Using the discovery index of {}", root_crate_path.display());
+            (index, vec![])
+        }
+        None => match build_index(root_crate_path, refresh) {
+            Err(e) => return vec![Err(DiscoveryError::from(e).into())],
+            Ok(built) => built,
+        },
+    };
+    let pack_roots = index
+        .pack_roots
+        .into_iter()
+        .map(Ok)
+        .chain(pack_errors.into_iter().map(Err))
+        .collect();
+    find_generators(&index.workspace_root, index.generator_manifests, pack_roots, config)
+}
+
+/// Parses the generators of one `cargo_generators.yaml`, outside of any dependency graph. Used to
+/// check a manifest before publishing it.
+pub fn parse_manifest(path: &Path) -> Vec<Result<Generator, Error>> {
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let cg_yaml = GeneratorManifest {
+        path: path.to_path_buf(),
+        package: local_package(dir),
+        features: None,
+    };
+    reject_duplicates(parse_generators(parse_yamls(vec![cg_yaml])))
+}
+
+/// Builds the index, checking the generator packs out on the way (`update_packs` is the `update`
+/// of `generator_pack_roots`). The packs that fail are returned apart and left out of the index.
+fn build_index(root_crate_path: &Path, update_packs: bool) -> Result<(Index, Vec<Error>), Error> {
+    let (workspace_root, dep_roots) = list_dep_root_dirs(root_crate_path)?;
+    let cg_yamls = find_yaml_files_in_dirs(&dep_roots);
+    let mut pack_roots = Vec::new();
+    let mut pack_errors = Vec::new();
+    for root in generator_pack_roots(&workspace_root, update_packs) {
+        match root {
+            Ok(root) => pack_roots.push(root),
+            Err(e) => pack_errors.push(e),
+        }
+    }
+    // Registry and git packages never change in place, only the local ones need watching. The
+    // workspace manifest may be virtual (not a package) but it lists the generator paths and the
+    // generator packs, whose lockfile and checkouts can change too.
+    let mut watched = vec![workspace_root.join("Cargo.toml"), workspace_root.join(LOCKFILE)];
+    for root in dep_roots.iter().filter(|root| root.local) {
+        watched.push(root.dir.join("Cargo.toml"));
+        watched.push(root.dir.join(GENERATORS_YAML));
+    }
+    watched.extend(pack_roots.iter().map(|root| root.join(GENERATORS_YAML)));
+    let index = Index::build(root_crate_path, workspace_root, &watched, cg_yamls, pack_roots)?;
+    // The index is only an optimisation, failing to write it must not fail the discovery. It is
+    // not written while a pack fails, so that the next run tries the pack again.
+    if pack_errors.is_empty() {
+        let _ = index.save();
+    }
+    Ok((index, pack_errors))
+}
+
+/// Parses the generator manifests found in the dependencies and in the extra package roots of the
+/// workspace, in that order. If a generator is defined more than once the first definition wins.
+fn find_generators(
+    workspace_root: &Path,
+    dep_yamls: Vec<GeneratorManifest>,
+    pack_roots: Vec<Result<PathBuf, Error>>,
+    config: &Config,
+) -> Vec<Result<Generator, Error>> {
+    let mut cg_yamls = dep_yamls;
+    let mut root_errors = Vec::new();
+    for root in extra_package_roots(workspace_root, config, pack_roots) {
+        match root {
+            Err(e) => root_errors.push(Err(e)),
+            Ok(dir) => {
+                let path = dir.join(GENERATORS_YAML);
+                if !path.is_file() {
+                    debug!("Skipped the generator path {}: no {}", dir.display(), GENERATORS_YAML);
+                } else if cg_yamls.iter().any(|cg_yaml| cg_yaml.path == path) {
+                    debug!("Skipped the generator path {}: already a dependency", dir.display());
+                } else {
+                    debug!("Found {} in the generator paths", path.display());
+                    cg_yamls.push(GeneratorManifest {
+                        path,
+                        package: local_package(&dir),
+                        features: None,
+                    });
+                }
+            }
+        }
+    }
+    // Parse each yaml
+    let parsed_yamls = parse_yamls(cg_yamls);
+    // Parse generators
+    let mut generators = reject_duplicates(parse_generators(parsed_yamls));
+    generators.extend(root_errors);
+    generators
+}
+
+#[derive(Debug)]
+struct PackageRoot {
+    dir: PathBuf,
+    package: PackageInfo,
+    /// Workspace members and path dependencies can change without the lockfile noticing.
+    local: bool,
+    features: Vec<String>,
+}
+
+/// Resolves the dependency graph of the root crate. Returns the workspace root and the roots of
+/// all the packages.
+fn list_dep_root_dirs<P>(root_crate_path: P) -> Result<(PathBuf, Vec<PackageRoot>), Error>
 where
     P: AsRef<Path> + AsRef<OsStr>,
 {
     let manifest_path = Path::new(&root_crate_path).join("Cargo.toml");
-    let metadata = metadata_deps(Some(&manifest_path), true).map_err(SyncFailure::new)?;
-    Ok(metadata
+    debug!("Resolving the dependencies of {}", manifest_path.display());
+    let metadata = MetadataCommand::new()
+        .manifest_path(&manifest_path)
+        .exec()
+        .map_err(SyncFailure::new)?;
+    let features: HashMap<&str, &Vec<String>> = metadata
+        .resolve
+        .iter()
+        .flat_map(|resolve| resolve.nodes.iter())
+        .map(|node| (node.id.repr.as_str(), &node.features))
+        .collect();
+    let dependency_paths = dependency_paths(&metadata);
+    let dep_roots = metadata
         .packages
         .iter()
-        .map(|package| Path::new(&package.manifest_path))
-        .filter_map(|path| path.parent()) // Drop the Cargo.toml at the end.
-        .map(|path| path.to_path_buf())
-        .collect())
+        .filter_map(|package| {
+            package
+                .manifest_path
+                .parent() // Drop the Cargo.toml at the end.
+                .map(|dir| PackageRoot {
+                    dir: dir.to_path_buf(),
+                    package: PackageInfo {
+                        id: package.id.repr.clone(),
+                        name: package.name.clone(),
+                        version: package.version.to_string(),
+                        dependency_path: dependency_paths
+                            .get(package.id.repr.as_str())
+                            .cloned()
+                            .unwrap_or_default(),
+                    },
+                    local: package.source.is_none(),
+                    features: features
+                        .get(package.id.repr.as_str())
+                        .map(|features| features.to_vec())
+                        .unwrap_or_default(),
+                })
+        })
+        .collect::<Vec<_>>();
+    for root in &dep_roots {
+        debug!("Package {} has its root at {}", root.package.id, root.dir.display());
+    }
+    Ok((metadata.workspace_root.clone(), dep_roots))
 }
 
-fn find_yaml_files_in_dirs(paths: &[PathBuf]) -> Vec<PathBuf> {
+/// The shortest way from the root crate to each package of the resolved graph, as package names.
+/// Virtual workspaces have no root crate, their members are the starting points.
+fn dependency_paths(metadata: &Metadata) -> HashMap<&str, Vec<String>> {
+    let names: HashMap<&str, &str> = metadata
+        .packages
+        .iter()
+        .map(|package| (package.id.repr.as_str(), package.name.as_str()))
+        .collect();
+    let resolve = match metadata.resolve {
+        Some(ref resolve) => resolve,
+        None => return HashMap::new(),
+    };
+    let deps: HashMap<&str, &Vec<PackageId>> = resolve
+        .nodes
+        .iter()
+        .map(|node| (node.id.repr.as_str(), &node.dependencies))
+        .collect();
+    let mut paths = HashMap::new();
+    let mut queue = VecDeque::new();
+    let starts = match resolve.root {
+        Some(ref root) => vec![root],
+        None => metadata.workspace_members.iter().collect(),
+    };
+    for start in starts {
+        let name = names.get(start.repr.as_str()).cloned().unwrap_or("");
+        paths.insert(start.repr.as_str(), vec![name.to_owned()]);
+        queue.push_back(start.repr.as_str());
+    }
+    while let Some(id) = queue.pop_front() {
+        let path = paths[id].clone();
+        for dep in deps.get(id).into_iter().flat_map(|deps| deps.iter()) {
+            let dep = dep.repr.as_str();
+            if !paths.contains_key(dep) {
+                let mut dep_path = path.clone();
+                dep_path.push(names.get(dep).cloned().unwrap_or("").to_owned());
+                paths.insert(dep, dep_path);
+                queue.push_back(dep);
+            }
+        }
+    }
     paths
+}
+
+fn find_yaml_files_in_dirs(roots: &[PackageRoot]) -> Vec<GeneratorManifest> {
+    roots
         .iter()
-        .map(|p| p.join("cargo_generators.yaml"))
-        .filter(|path| path.is_file())
+        .map(|root| GeneratorManifest {
+            path: root.dir.join(GENERATORS_YAML),
+            package: root.package.clone(),
+            features: Some(root.features.clone()),
+        })
+        .filter(|cg_yaml| {
+            let found = cg_yaml.path.is_file();
+            if found {
+                debug!("Found {}", cg_yaml.path.display());
+            } else {
+                trace!("Skipped {}: no {}", cg_yaml.package.id, GENERATORS_YAML);
+            }
+            found
+        })
         .collect()
 }
 
-fn parse_yamls(paths: Vec<PathBuf>) -> Vec<Result<Yaml, Error>> {
-    let mut results: Vec<Result<Yaml, Error>> = Vec::new();
+fn parse_yamls(cg_yamls: Vec<GeneratorManifest>) -> Vec<Result<(Yaml, GeneratorManifest), Error>> {
+    let mut results: Vec<Result<(Yaml, GeneratorManifest), Error>> = Vec::new();
 
-    for path in paths {
+    for cg_yaml in cg_yamls {
         let mut yaml_str = String::new();
-        // FIXME: panics
-        File::open(path)
-            .unwrap()
-            .read_to_string(&mut yaml_str)
-            .unwrap();
+        let read = File::open(&cg_yaml.path)
+            .and_then(|mut file| file.read_to_string(&mut yaml_str));
+        if let Err(err) = read {
+            results.push(Err(ManifestError::new(&cg_yaml.path, err).into()));
+            continue;
+        }
         match YamlLoader::load_from_str(&yaml_str) {
-            Err(err) => results.push(Err(Error::from(err))),
-            Ok(yamls) => match yamls[0].as_vec() {
-                None => results.push(Err(err_msg("A generators YAML file is not an array"))),
-                Some(yamls) => results.extend(yamls.iter().map(|yaml| Ok(yaml.clone()))),
+            Err(err) => results.push(Err(ManifestError::new(&cg_yaml.path, err).into())),
+            Ok(yamls) => match yamls.first().and_then(|yaml| yaml.as_vec()) {
+                None => results.push(Err(ManifestError::new(
+                    &cg_yaml.path,
+                    "A generators YAML file is not an array",
+                ).into())),
+                Some(yamls) => results.extend(
+                    yamls
+                        .iter()
+                        .map(|yaml| Ok((yaml.clone(), cg_yaml.clone()))),
+                ),
             },
         }
     }
     results
 }
 
-fn parse_generators(yamls: Vec<Result<Yaml, Error>>) -> Vec<Result<Generator, Error>> {
+fn parse_generators(
+    yamls: Vec<Result<(Yaml, GeneratorManifest), Error>>,
+) -> Vec<Result<Generator, Error>> {
     yamls
         .into_iter()
-        .map(|res| res.and_then(|yaml| Generator::try_from_yaml(&yaml)))
+        .map(|res| {
+            res.and_then(|(yaml, cg_yaml)| {
+                let mut generator = Generator::try_from_yaml(&yaml).map_err(|e| {
+                    debug!("Skipped a generator of {}: {}", cg_yaml.path.display(), e);
+                    ManifestError::new(&cg_yaml.path, e)
+                })?;
+                generator.set_package(cg_yaml.package, cg_yaml.path);
+                if let Some(ref features) = cg_yaml.features {
+                    generator.missing_features = generator
+                        .requires_features
+                        .iter()
+                        .filter(|feature| !features.contains(feature))
+                        .cloned()
+                        .collect();
+                }
+                debug!(
+                    "Parsed generator {} (factory {}, {} args{}) from {}",
+                    generator.name,
+                    generator.factory,
+                    generator.args.len(),
+                    if generator.missing_features.is_empty() {
+                        String::new()
+                    } else {
+                        format!(", missing the features {}", generator.missing_features.join(", "))
+                    },
+                    generator.manifest_path.display()
+                );
+                Ok(generator)
+            })
+        })
+        .collect()
+}
+
+/// Generator names and aliases share one namespace. A generator that reuses an identifier already
+/// taken by an earlier one is dropped.
+fn reject_duplicates(generators: Vec<Result<Generator, Error>>) -> Vec<Result<Generator, Error>> {
+    let mut ids = HashSet::new();
+    generators
+        .into_iter()
+        .map(|res| {
+            res.and_then(|generator| {
+                let taken = Some(&generator.name)
+                    .into_iter()
+                    .chain(generator.aliases.iter())
+                    .find(|id| ids.contains(*id))
+                    .cloned();
+                if let Some(ref id) = taken {
+                    debug!(
+                        "Skipped generator {} of {}: {} is already taken",
+                        generator.name,
+                        generator.manifest_path.display(),
+                        id
+                    );
+                }
+                match taken {
+                    None => {
+                        ids.insert(generator.name.clone());
+                        ids.extend(generator.aliases.iter().cloned());
+                        Ok(generator)
+                    }
+                    Some(id) if id == generator.name => Err(ManifestError::new(
+                        &generator.manifest_path,
+                        format!(
+                            "Generator {} is defined more than once, only the first definition \
+                             is used",
+                            id
+                        ),
+                    ).into()),
+                    Some(id) => Err(ManifestError::new(
+                        &generator.manifest_path,
+                        format!(
+                            "The alias {} of generator {} is already taken, the generator is \
+                             skipped",
+                            id, generator.name
+                        ),
+                    ).into()),
+                }
+            })
+        })
         .collect()
 }
 
@@ -148,6 +848,157 @@ mod from_yaml_test {
         assert!(Generator::try_from_yaml(&yaml[0]).is_err());
     }
 
+    #[test]
+    fn it_parses_the_required_features_from_yaml() {
+        let yaml =
+            YamlLoader::load_from_str("name: a\nfactory: f\nrequires-features: [sqlx, tls]")
+                .unwrap();
+        assert_eq!(
+            vec!["sqlx", "tls"],
+            Generator::try_from_yaml(&yaml[0]).unwrap().requires_features
+        );
+    }
+
+    #[test]
+    fn it_requires_no_features_by_default() {
+        let yaml = YamlLoader::load_from_str("name: a\nfactory: f").unwrap();
+        assert!(Generator::try_from_yaml(&yaml[0]).unwrap().is_available());
+    }
+
+    #[test]
+    fn it_fails_if_the_required_features_are_not_an_array() {
+        let yaml = YamlLoader::load_from_str("name: a\nfactory: f\nrequires-features: sqlx")
+            .unwrap();
+        assert!(Generator::try_from_yaml(&yaml[0]).is_err());
+    }
+
+    #[test]
+    fn it_parses_hidden_deprecated_and_aliases_from_yaml() {
+        let yaml = YamlLoader::load_from_str(
+            "name: a.y\nfactory: f\nhidden: true\ndeprecated: use a.x instead\naliases: [a.z]",
+        ).unwrap();
+        let generator = Generator::try_from_yaml(&yaml[0]).unwrap();
+        assert!(generator.is_hidden());
+        assert_eq!(Some("use a.x instead"), generator.deprecated());
+        assert!(generator.is_known_as("a.y"));
+        assert!(generator.is_known_as("a.z"));
+        assert!(!generator.is_known_as("a.x"));
+    }
+
+    #[test]
+    fn it_is_not_hidden_nor_deprecated_by_default() {
+        let yaml = YamlLoader::load_from_str("name: a\nfactory: f").unwrap();
+        let generator = Generator::try_from_yaml(&yaml[0]).unwrap();
+        assert!(!generator.is_hidden());
+        assert_eq!(None, generator.deprecated());
+        assert_eq!(0, generator.aliases().len());
+    }
+
+    #[test]
+    fn it_fails_if_hidden_is_not_a_boolean() {
+        let yaml = YamlLoader::load_from_str("name: a\nfactory: f\nhidden: yes please").unwrap();
+        assert!(Generator::try_from_yaml(&yaml[0]).is_err());
+    }
+
+    #[test]
+    fn it_parses_the_category_and_tags_from_yaml() {
+        let yaml = YamlLoader::load_from_str(
+            "name: a\nfactory: f\ncategory: testing\ntags: [web, http]",
+        ).unwrap();
+        let generator = Generator::try_from_yaml(&yaml[0]).unwrap();
+        assert_eq!(Some("testing"), generator.category());
+        assert_eq!(vec!["web", "http"], generator.tags());
+    }
+
+    #[test]
+    fn it_parses_the_chain_examples_and_notes_from_yaml() {
+        let yaml = YamlLoader::load_from_str(
+            "name: a\nfactory: f\nchain: [a.model, a.test]\nexamples: [cargo gen a user]\n\
+             notes: Run it from the crate root",
+        ).unwrap();
+        let generator = Generator::try_from_yaml(&yaml[0]).unwrap();
+        assert_eq!(vec!["a.model", "a.test"], generator.chain());
+        assert_eq!(vec!["cargo gen a user"], generator.examples());
+        assert_eq!(Some("Run it from the crate root"), generator.notes());
+    }
+
+    #[test]
+    fn it_matches_generators_by_category_and_tags() {
+        let yaml = YamlLoader::load_from_str(
+            "name: a\nfactory: f\ncategory: testing\ntags: [web, http]",
+        ).unwrap();
+        let generator = Generator::try_from_yaml(&yaml[0]).unwrap();
+        assert!(generator.matches(None, &[]));
+        assert!(generator.matches(Some("testing"), &["web".to_owned()]));
+        assert!(generator.matches(None, &["web".to_owned(), "http".to_owned()]));
+        assert!(!generator.matches(Some("models"), &[]));
+        assert!(!generator.matches(None, &["web".to_owned(), "cli".to_owned()]));
+    }
+
+    #[test]
+    fn it_fails_if_the_category_is_not_a_string() {
+        let yaml = YamlLoader::load_from_str("name: a\nfactory: f\ncategory: [a, b]").unwrap();
+        assert!(Generator::try_from_yaml(&yaml[0]).is_err());
+    }
+
+    #[test]
+    fn it_parses_the_about_version_and_args_from_yaml() {
+        let yaml = YamlLoader::load_from_str(
+            "name: a\nfactory: f\nabout: An app\nversion: 0.1\nargs:\n  - NAME:\n      \
+             help: The name\n      required: true\n      index: 1\n  - crate-root:\n      \
+             long: crate-root\n      default_value: \".\"\n  - verbose:",
+        ).unwrap();
+        let generator = Generator::try_from_yaml(&yaml[0]).unwrap();
+        assert_eq!(Some("An app"), generator.about());
+        assert_eq!(Some("0.1"), generator.version());
+        assert_eq!(
+            vec![
+                GeneratorArg {
+                    name: "NAME".to_owned(),
+                    help: Some("The name".to_owned()),
+                    required: true,
+                    index: Some(1),
+                    ..GeneratorArg::default()
+                },
+                GeneratorArg {
+                    name: "crate-root".to_owned(),
+                    long: Some("crate-root".to_owned()),
+                    default_value: Some(".".to_owned()),
+                    ..GeneratorArg::default()
+                },
+                GeneratorArg {
+                    name: "verbose".to_owned(),
+                    ..GeneratorArg::default()
+                },
+            ],
+            generator.args()
+        );
+    }
+
+    #[test]
+    fn it_parses_the_prompts_of_the_args_from_yaml() {
+        let yaml = YamlLoader::load_from_str(
+            "name: a\nfactory: f\nargs:\n  - NAME:\n      prompt: What is it called?\n  \
+             - force:\n      long: force",
+        ).unwrap();
+        let generator = Generator::try_from_yaml(&yaml[0]).unwrap();
+        assert_eq!(Some("What is it called?"), generator.args()[0].prompt.as_deref());
+        assert!(generator.args()[0].takes_value());
+        assert_eq!(None, generator.args()[1].prompt);
+        assert!(!generator.args()[1].takes_value());
+
+        let yaml =
+            YamlLoader::load_from_str("name: a\nfactory: f\nargs:\n  - NAME:\n      prompt: 1")
+                .unwrap();
+        assert!(Generator::try_from_yaml(&yaml[0]).is_err());
+    }
+
+    #[test]
+    fn it_fails_if_an_arg_is_not_named() {
+        let yaml = YamlLoader::load_from_str("name: a\nfactory: f\nargs: [NAME]").unwrap();
+        assert!(Generator::try_from_yaml(&yaml[0]).is_err());
+    }
+
     #[test]
     fn it_fails_if_generator_config_is_not_a_hash() {
         let yaml = YamlLoader::load_from_str("[]").unwrap();
@@ -164,10 +1015,13 @@ mod find_all_test {
     use self::cargo::util::Config as CargoConfig;
     use self::cargo_gen_helpers::test_helpers::create_empty_crate;
     use self::cargo_gen_helpers::{create_file, modify_file};
+    use roots::isolate_home;
     use super::*;
+    use index;
 
     #[test]
     fn it_finds_generators_in_the_current_dir_and_prints_their_names() {
+        isolate_home();
         let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
         create_file(
             crate_dir.path().join("cargo_generators.yaml"),
@@ -175,7 +1029,7 @@ mod find_all_test {
         ).unwrap();
         assert_eq!(
             vec!["root.gen1", "root.gen2"],
-            find_all(crate_dir.path())
+            find_all(crate_dir.path(), &Config::default())
                 .into_iter()
                 .map(|res| res.map(|generator| generator.name)
                     .unwrap_or_else(|e| format!("{}", e)))
@@ -185,6 +1039,7 @@ mod find_all_test {
 
     #[test]
     fn it_finds_generators_in_a_dependency_and_prints_their_names() {
+        isolate_home();
         let dep_crate_dir = create_empty_crate("cargo-gen-dep").unwrap();
         create_file(
             dep_crate_dir.path().join("cargo_generators.yaml"),
@@ -204,7 +1059,7 @@ mod find_all_test {
 
         assert_eq!(
             vec!["dep.gen1", "dep.gen2"],
-            find_all(crate_dir.path())
+            find_all(crate_dir.path(), &Config::default())
                 .into_iter()
                 .map(|res| res.map(|generator| generator.name)
                     .unwrap_or_else(|e| format!("{}", e)))
@@ -212,8 +1067,36 @@ mod find_all_test {
         );
     }
 
+    #[test]
+    fn it_records_how_the_package_of_a_generator_is_reached() {
+        fn depend_on(crate_dir: &Path, name: &str, dep_dir: &Path) {
+            modify_file(crate_dir.join("Cargo.toml"), |contents| {
+                let deps_str = "[dependencies]\n";
+                let new_deps_str = format!("{}{} = {{ path = {:?} }}\n", deps_str, name, dep_dir);
+                Ok(Some(contents.replace(deps_str, &new_deps_str)))
+            }).unwrap();
+        }
+        isolate_home();
+        let leaf_crate_dir = create_empty_crate("cargo-gen-leaf").unwrap();
+        create_file(
+            leaf_crate_dir.path().join("cargo_generators.yaml"),
+            "- name: leaf.gen1\n  factory: f",
+        ).unwrap();
+        let mid_crate_dir = create_empty_crate("cargo-gen-mid").unwrap();
+        depend_on(mid_crate_dir.path(), "cargo-gen-leaf", leaf_crate_dir.path());
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        depend_on(crate_dir.path(), "cargo-gen-mid", mid_crate_dir.path());
+
+        let generator = find_all(crate_dir.path(), &Config::default()).remove(0).unwrap();
+        assert_eq!(
+            vec!["cargo-gen-test", "cargo-gen-mid", "cargo-gen-leaf"],
+            generator.package().dependency_path
+        );
+    }
+
     #[test]
     fn it_finds_generators_in_a_member_package_and_prints_their_names() {
+        isolate_home();
         let root_crate_dir = create_empty_crate("cargo-gen-test").unwrap();
         modify_file(root_crate_dir.path().join("Cargo.toml"), |contents| {
             let deps_str = "[dependencies]\n";
@@ -242,7 +1125,7 @@ mod find_all_test {
 
         assert_eq!(
             vec!["member.gen1", "member.gen2"],
-            find_all(root_crate_dir.path())
+            find_all(root_crate_dir.path(), &Config::default())
                 .into_iter()
                 .map(|res| res.map(|generator| generator.name)
                     .unwrap_or_else(|e| format!("{}", e)))
@@ -250,8 +1133,205 @@ mod find_all_test {
         );
     }
 
+    #[test]
+    fn it_finds_the_same_generators_through_the_index() {
+        isolate_home();
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        create_file(
+            crate_dir.path().join("cargo_generators.yaml"),
+            "- name: root.gen1\n  factory: f",
+        ).unwrap();
+        let names = |refresh| {
+            find_all_cached(crate_dir.path(), refresh, &Config::default())
+                .into_iter()
+                .map(|res| res.map(|generator| generator.name)
+                    .unwrap_or_else(|e| format!("{}", e)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["root.gen1"], names(false));
+        // A rebuilt index loses the comment.
+        let index_path = index::index_path(crate_dir.path()).unwrap();
+        let is_reused = || {
+            let mut content = String::new();
+            File::open(&index_path).unwrap().read_to_string(&mut content).unwrap();
+            content.ends_with("# reused\n")
+        };
+        modify_file(&index_path, |content| Ok(Some(content + "\n# reused\n"))).unwrap();
+        assert_eq!(vec!["root.gen1"], names(false));
+        assert!(is_reused());
+        assert_eq!(vec!["root.gen1"], names(true));
+        assert!(!is_reused());
+    }
+
+    #[test]
+    fn it_notices_a_generator_manifest_added_after_indexing() {
+        isolate_home();
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        assert_eq!(0, find_all_cached(crate_dir.path(), false, &Config::default()).len());
+        create_file(
+            crate_dir.path().join("cargo_generators.yaml"),
+            "- name: root.gen1\n  factory: f",
+        ).unwrap();
+        assert_eq!(
+            vec!["root.gen1"],
+            find_all_cached(crate_dir.path(), false, &Config::default())
+                .into_iter()
+                .map(|res| res.map(|generator| generator.name)
+                    .unwrap_or_else(|e| format!("{}", e)))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn it_finds_generators_in_the_workspace_generator_paths() {
+        isolate_home();
+        let gen_crate_dir = create_empty_crate("cargo-gen-shared").unwrap();
+        create_file(
+            gen_crate_dir.path().join("cargo_generators.yaml"),
+            "- name: shared.gen1\n  factory: f",
+        ).unwrap();
+
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        modify_file(crate_dir.path().join("Cargo.toml"), |contents| {
+            Ok(Some(format!(
+                "{}\n[workspace.metadata.cargo-gen]\ngenerator-paths = [{:?}]\n",
+                contents,
+                gen_crate_dir.path().as_os_str()
+            )))
+        }).unwrap();
+
+        assert_eq!(
+            vec!["shared.gen1"],
+            find_all(crate_dir.path(), &Config::default())
+                .into_iter()
+                .map(|res| res.map(|generator| generator.name)
+                    .unwrap_or_else(|e| format!("{}", e)))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn it_moves_a_generator_pack_to_the_latest_commit_of_its_branch_on_refresh() {
+        isolate_home();
+        let repo = create_empty_crate("cargo-gen-pack").unwrap();
+        let git = |args: &[&str]| {
+            let status = ::std::process::Command::new("git")
+                .arg("-C")
+                .arg(repo.path())
+                .args(["-c", "user.name=cargo-gen", "-c", "user.email=cargo-gen@example.com"])
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        let commit = |name: &str| {
+            let yaml = format!("- name: {}\n  factory: f", name);
+            create_file(repo.path().join(GENERATORS_YAML), &yaml).unwrap();
+            git(&["add", "--all"]);
+            git(&["commit", "--quiet", "-m", name]);
+        };
+        git(&["init", "--quiet"]);
+        commit("pack.gen1");
+
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        modify_file(crate_dir.path().join("Cargo.toml"), |contents| {
+            Ok(Some(format!(
+                "{}\n[workspace.metadata.cargo-gen]\n\
+                 generator-packs = [{{ git = \"file://{}\" }}]\n",
+                contents,
+                repo.path().display()
+            )))
+        }).unwrap();
+        let names = |refresh| {
+            find_all_cached(crate_dir.path(), refresh, &Config::default())
+                .into_iter()
+                .map(|res| res.map(|generator| generator.name)
+                    .unwrap_or_else(|e| format!("{}", e)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["pack.gen1"], names(false));
+
+        commit("pack.gen2");
+        // The lockfile keeps the pack where it was until a refresh.
+        assert_eq!(vec!["pack.gen1"], names(false));
+        assert_eq!(vec!["pack.gen2"], names(true));
+        assert_eq!(vec!["pack.gen2"], names(false));
+    }
+
+    #[test]
+    fn it_keeps_the_first_definition_of_a_duplicate_generator() {
+        isolate_home();
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        create_file(
+            crate_dir.path().join("cargo_generators.yaml"),
+            "- name: root.gen1\n  factory: f\n- name: root.gen1\n  factory: g",
+        ).unwrap();
+        assert_eq!(
+            vec!["root.gen1", "ERROR"],
+            find_all(crate_dir.path(), &Config::default())
+                .into_iter()
+                .map(|res| res.map(|generator| generator.name)
+                    .unwrap_or("ERROR".to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn it_marks_generators_whose_required_features_are_not_enabled() {
+        isolate_home();
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        create_file(
+            crate_dir.path().join("cargo_generators.yaml"),
+            "- name: root.gen1\n  factory: f\n  requires-features: [sqlx]",
+        ).unwrap();
+        let generator = find_all(crate_dir.path(), &Config::default()).remove(0).unwrap();
+        assert!(!generator.is_available());
+        assert_eq!(vec!["sqlx"], generator.missing_features());
+        assert!(generator.unavailable_reason().unwrap().contains("sqlx is not"));
+    }
+
+    #[test]
+    fn it_offers_generators_whose_required_features_are_enabled() {
+        isolate_home();
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        modify_file(crate_dir.path().join("Cargo.toml"), |contents| {
+            Ok(Some(format!(
+                "{}\n[features]\ndefault = [\"sqlx\"]\nsqlx = []\n",
+                contents
+            )))
+        }).unwrap();
+        create_file(
+            crate_dir.path().join("cargo_generators.yaml"),
+            "- name: root.gen1\n  factory: f\n  requires-features: [sqlx]",
+        ).unwrap();
+        let generator = find_all(crate_dir.path(), &Config::default()).remove(0).unwrap();
+        assert!(generator.is_available());
+        assert_eq!(None, generator.unavailable_reason());
+    }
+
+    #[test]
+    fn it_rejects_generators_that_reuse_an_alias() {
+        isolate_home();
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        create_file(
+            crate_dir.path().join("cargo_generators.yaml"),
+            "- name: root.gen1\n  factory: f\n  aliases: [root.old]\n\
+             - name: root.old\n  factory: f\n\
+             - name: root.gen2\n  factory: f\n  aliases: [root.gen1]",
+        ).unwrap();
+        assert_eq!(
+            vec!["root.gen1", "ERROR", "ERROR"],
+            find_all(crate_dir.path(), &Config::default())
+                .into_iter()
+                .map(|res| res.map(|generator| generator.name)
+                    .unwrap_or("ERROR".to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn it_skips_and_reports_invalid_generators() {
+        isolate_home();
         let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
         create_file(
             crate_dir.path().join("cargo_generators.yaml"),
@@ -259,7 +1339,25 @@ mod find_all_test {
         ).unwrap();
         assert_eq!(
             vec!["ERROR", "root.gen2"],
-            find_all(crate_dir.path())
+            find_all(crate_dir.path(), &Config::default())
+                .into_iter()
+                .map(|res| res.map(|generator| generator.name)
+                    .unwrap_or("ERROR".to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn it_parses_a_manifest_on_its_own() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let path = crate_dir.path().join("cargo_generators.yaml");
+        create_file(
+            &path,
+            "- name: root.gen1\n  factory: f\n- name: root.gen1\n  factory: f\n- factory: f",
+        ).unwrap();
+        assert_eq!(
+            vec!["root.gen1", "ERROR", "ERROR"],
+            parse_manifest(&path)
                 .into_iter()
                 .map(|res| res.map(|generator| generator.name)
                     .unwrap_or("ERROR".to_string()))
@@ -269,11 +1367,12 @@ mod find_all_test {
 
     #[test]
     fn it_fails_on_invalid_yaml() {
+        isolate_home();
         let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
         create_file(crate_dir.path().join("cargo_generators.yaml"), "[{]}").unwrap();
         assert_eq!(
             vec!["ERROR"],
-            find_all(crate_dir.path())
+            find_all(crate_dir.path(), &Config::default())
                 .into_iter()
                 .map(|res| res.map(|generator| generator.name)
                     .unwrap_or("ERROR".to_string()))
@@ -283,11 +1382,12 @@ mod find_all_test {
 
     #[test]
     fn it_fails_if_yaml_is_not_an_array() {
+        isolate_home();
         let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
         create_file(crate_dir.path().join("cargo_generators.yaml"), "{}").unwrap();
         assert_eq!(
             vec!["ERROR"],
-            find_all(crate_dir.path())
+            find_all(crate_dir.path(), &Config::default())
                 .into_iter()
                 .map(|res| res.map(|generator| generator.name)
                     .unwrap_or("ERROR".to_string()))
@@ -0,0 +1,542 @@
+//! Generator packs fetched from git repositories.
+//!
+//! A pack is configured in `[workspace.metadata.cargo-gen]` of the workspace manifest:
+//!
+//! ```toml
+//! generator-packs = [
+//!     { git = "https://example.com/generators.git", tag = "v1.0" },
+//!     { git = "file:///srv/git/generators", branch = "main", path = "web" },
+//! ]
+//! ```
+//!
+//! Repositories are fetched into `git/db` of the cargo-gen home and each commit gets its own
+//! checkout in `git/checkouts`. The commit a reference resolved to is recorded in `cargo-gen.lock`
+//! next to the workspace manifest and reused until the pack configuration changes. `--refresh`
+//! moves the packs that follow a branch to its latest commit.
+extern crate toml;
+
+use std::ffi::OsStr;
+use std::fs::{create_dir_all, remove_dir_all, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use failure::{err_msg, Error};
+use hash::stable_hash;
+
+/// The name of the lockfile that records the resolved commits of the generator packs.
+pub const LOCKFILE: &str = "cargo-gen.lock";
+
+/// Marks a checkout as complete. A checkout interrupted halfway is discarded and done again.
+const CHECKOUT_OK: &str = ".cargo-gen-ok";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GitReference {
+    /// Whatever the `HEAD` of the remote repository points to.
+    DefaultBranch,
+    Branch(String),
+    Tag(String),
+    Rev(String),
+}
+
+impl GitReference {
+    fn to_lock_string(&self) -> String {
+        match *self {
+            GitReference::DefaultBranch => "default".to_owned(),
+            GitReference::Branch(ref branch) => format!("branch={}", branch),
+            GitReference::Tag(ref tag) => format!("tag={}", tag),
+            GitReference::Rev(ref rev) => format!("rev={}", rev),
+        }
+    }
+
+    fn to_rev_spec(&self) -> String {
+        match *self {
+            GitReference::DefaultBranch => "refs/cargo-gen/HEAD^{commit}".to_owned(),
+            GitReference::Branch(ref branch) => format!("refs/heads/{}^{{commit}}", branch),
+            GitReference::Tag(ref tag) => format!("refs/tags/{}^{{commit}}", tag),
+            GitReference::Rev(ref rev) => format!("{}^{{commit}}", rev),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitPack {
+    pub url: String,
+    pub reference: GitReference,
+    /// The package root inside the repository if it is not the repository root.
+    pub path: Option<String>,
+}
+
+impl GitPack {
+    pub fn try_from_toml(value: &toml::Value) -> Result<GitPack, Error> {
+        let string_of = |key: &str| -> Result<Option<String>, Error> {
+            match value.get(key) {
+                None => Ok(None),
+                Some(v) => v.as_str()
+                    .map(|s| Some(s.to_owned()))
+                    .ok_or_else(|| format_err!("The {} of a generator pack is not a string", key)),
+            }
+        };
+        let url = string_of("git")?
+            .ok_or_else(|| err_msg("A generator pack does not have a git URL"))?;
+        let reference = match (string_of("branch")?, string_of("tag")?, string_of("rev")?) {
+            (None, None, None) => GitReference::DefaultBranch,
+            (Some(branch), None, None) => GitReference::Branch(branch),
+            (None, Some(tag), None) => GitReference::Tag(tag),
+            (None, None, Some(rev)) => GitReference::Rev(rev),
+            _ => bail!(
+                "Generator pack {} can only have one of branch, tag and rev",
+                url
+            ),
+        };
+        let pack = GitPack {
+            url,
+            reference,
+            path: string_of("path")?,
+        };
+        pack.check()?;
+        Ok(pack)
+    }
+
+    /// Whether the commit of the pack moves on, as opposed to a tag or a rev.
+    pub fn follows_a_branch(&self) -> bool {
+        match self.reference {
+            GitReference::DefaultBranch | GitReference::Branch(_) => true,
+            GitReference::Tag(_) | GitReference::Rev(_) => false,
+        }
+    }
+
+    /// Rejects the values git would take for options, and a path that leaves the checkout.
+    fn check(&self) -> Result<(), Error> {
+        let reference = match self.reference {
+            GitReference::DefaultBranch => None,
+            GitReference::Branch(ref reference)
+            | GitReference::Tag(ref reference)
+            | GitReference::Rev(ref reference) => Some(reference),
+        };
+        for value in Some(&self.url).into_iter().chain(reference) {
+            if value.starts_with('-') {
+                bail!("Generator pack {} has an invalid reference or URL: {}", self.url, value);
+            }
+        }
+        if let Some(ref path) = self.path {
+            let is_inside = Path::new(path)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+            if !is_inside {
+                bail!(
+                    "The path {} of generator pack {} is not a relative path inside the repository",
+                    path,
+                    self.url
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes sure the pack is checked out at the `locked_commit`, or at the commit its reference
+    /// currently resolves to. Returns the package root and the commit.
+    pub fn checkout(
+        &self,
+        home: &Path,
+        locked_commit: Option<&str>,
+    ) -> Result<(PathBuf, String), Error> {
+        self.check()?;
+        if let Some(commit) = locked_commit {
+            if commit.is_empty() || !commit.chars().all(|c| c.is_ascii_hexdigit()) {
+                bail!(
+                    "The locked commit {} of generator pack {} is not a commit id",
+                    commit,
+                    self.url
+                );
+            }
+        }
+        let ident = self.ident();
+        let db = home.join("git").join("db").join(&ident);
+        if !db.join("HEAD").is_file() {
+            create_dir_all(&db)?;
+            git(Some(&db), &["init", "--quiet", "--bare"])?;
+        }
+        let commit = match locked_commit {
+            Some(commit) => {
+                if !has_commit(&db, commit) {
+                    self.fetch(&db)?;
+                }
+                if !has_commit(&db, commit) {
+                    bail!(
+                        "The locked commit {} of generator pack {} no longer exists",
+                        commit,
+                        self.url
+                    );
+                }
+                commit.to_owned()
+            }
+            None => {
+                self.fetch(&db)?;
+                git(Some(&db), &["rev-parse", "--verify", &self.reference.to_rev_spec()])
+                    .map_err(|_| {
+                        format_err!(
+                            "Could not find {} in generator pack {}",
+                            self.reference.to_lock_string(),
+                            self.url
+                        )
+                    })?
+            }
+        };
+
+        let short_commit: String = commit.chars().take(12).collect();
+        let checkout_dir = home.join("git")
+            .join("checkouts")
+            .join(&ident)
+            .join(short_commit);
+        if !checkout_dir.join(CHECKOUT_OK).is_file() {
+            if checkout_dir.exists() {
+                remove_dir_all(&checkout_dir)?;
+            }
+            create_dir_all(&checkout_dir)?;
+            git(
+                None,
+                &[
+                    OsStr::new("clone"),
+                    OsStr::new("--quiet"),
+                    OsStr::new("--no-checkout"),
+                    db.as_os_str(),
+                    checkout_dir.as_os_str(),
+                ],
+            )?;
+            git(
+                Some(&checkout_dir),
+                &["checkout", "--quiet", "--detach", &commit],
+            )?;
+            File::create(checkout_dir.join(CHECKOUT_OK))?;
+        }
+
+        let root = match self.path {
+            Some(ref path) => checkout_dir.join(path),
+            None => checkout_dir,
+        };
+        Ok((root, commit))
+    }
+
+    fn fetch(&self, db: &Path) -> Result<(), Error> {
+        git(
+            Some(db),
+            &[
+                "fetch",
+                "--quiet",
+                "--force",
+                "--tags",
+                "--",
+                &self.url,
+                "+refs/heads/*:refs/heads/*",
+                "+HEAD:refs/cargo-gen/HEAD",
+            ],
+        ).map(|_| ())
+            .map_err(|e| format_err!("Fetching generator pack {} failed: {}", self.url, e))
+    }
+
+    /// A directory name that is readable and unique per URL.
+    fn ident(&self) -> String {
+        let name = self.url
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or("")
+            .trim_end_matches(".git");
+        format!("{}-{}", name, stable_hash(self.url.as_bytes()))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct LockedPack {
+    url: String,
+    reference: String,
+    commit: String,
+}
+
+/// The resolved commits of the generator packs of a workspace.
+#[derive(Debug, PartialEq)]
+pub struct PackLock {
+    path: PathBuf,
+    packs: Vec<LockedPack>,
+    changed: bool,
+}
+
+impl PackLock {
+    pub fn load(workspace_root: &Path) -> Result<PackLock, Error> {
+        let path = workspace_root.join(LOCKFILE);
+        let mut lock = PackLock {
+            path,
+            packs: vec![],
+            changed: false,
+        };
+        if !lock.path.is_file() {
+            return Ok(lock);
+        }
+        let mut toml_str = String::new();
+        File::open(&lock.path)?.read_to_string(&mut toml_str)?;
+        let value = toml_str
+            .parse::<toml::Value>()
+            .map_err(|e| format_err!("{} is not valid: {}", lock.path.display(), e))?;
+        let packs = value
+            .get("pack")
+            .and_then(|packs| packs.as_array())
+            .map(|packs| packs.as_slice())
+            .unwrap_or(&[]);
+        for pack in packs {
+            let string_of = |key: &str| {
+                pack.get(key)
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_owned())
+                    .ok_or_else(|| format_err!("{} has a pack without {}", LOCKFILE, key))
+            };
+            lock.packs.push(LockedPack {
+                url: string_of("git")?,
+                reference: string_of("reference")?,
+                commit: string_of("commit")?,
+            });
+        }
+        Ok(lock)
+    }
+
+    pub fn commit_of(&self, pack: &GitPack) -> Option<&str> {
+        let reference = pack.reference.to_lock_string();
+        self.packs
+            .iter()
+            .find(|locked| locked.url == pack.url && locked.reference == reference)
+            .map(|locked| locked.commit.as_str())
+    }
+
+    pub fn record(&mut self, pack: &GitPack, commit: &str) {
+        if self.commit_of(pack) == Some(commit) {
+            return;
+        }
+        let reference = pack.reference.to_lock_string();
+        self.packs
+            .retain(|locked| !(locked.url == pack.url && locked.reference == reference));
+        self.packs.push(LockedPack {
+            url: pack.url.clone(),
+            reference,
+            commit: commit.to_owned(),
+        });
+        self.changed = true;
+    }
+
+    /// Forgets the packs that are no longer configured.
+    pub fn retain(&mut self, packs: &[GitPack]) {
+        let count = self.packs.len();
+        self.packs.retain(|locked| {
+            packs.iter().any(|pack| {
+                locked.url == pack.url && locked.reference == pack.reference.to_lock_string()
+            })
+        });
+        self.changed = self.changed || count != self.packs.len();
+    }
+
+    /// Writes the lockfile if anything changed. An empty lockfile is not created.
+    pub fn save(&self) -> Result<(), Error> {
+        if !self.changed || (self.packs.is_empty() && !self.path.exists()) {
+            return Ok(());
+        }
+        let packs = self.packs
+            .iter()
+            .map(|locked| {
+                let mut table = toml::value::Table::new();
+                table.insert("git".to_owned(), toml::Value::String(locked.url.clone()));
+                table.insert(
+                    "reference".to_owned(),
+                    toml::Value::String(locked.reference.clone()),
+                );
+                table.insert(
+                    "commit".to_owned(),
+                    toml::Value::String(locked.commit.clone()),
+                );
+                toml::Value::Table(table)
+            })
+            .collect();
+        let mut lock = toml::value::Table::new();
+        lock.insert("pack".to_owned(), toml::Value::Array(packs));
+        let content = format!(
+            "# This file is generated by cargo-gen. It records the commits of the generator \
+             packs.\n{}",
+            toml::Value::Table(lock)
+        );
+        File::create(&self.path)?.write_all(content.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn has_commit(db: &Path, commit: &str) -> bool {
+    git(Some(db), &["cat-file", "-e", &format!("{}^{{commit}}", commit)]).is_ok()
+}
+
+fn git<S: AsRef<OsStr>>(dir: Option<&Path>, args: &[S]) -> Result<String, Error> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.arg("-C").arg(dir);
+    }
+    let output = command
+        .args(args)
+        .output()
+        .map_err(|e| format_err!("Could not run git: {}", e))?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+#[cfg(test)]
+mod git_test {
+    extern crate cargo_gen_helpers;
+
+    use self::cargo_gen_helpers::test_helpers::create_empty_crate;
+    use self::cargo_gen_helpers::create_file;
+    use super::*;
+
+    fn commit_all(repo: &Path, message: &str) -> String {
+        git(Some(repo), &["add", "--all"]).unwrap();
+        git(
+            Some(repo),
+            &[
+                "-c",
+                "user.name=cargo-gen",
+                "-c",
+                "user.email=cargo-gen@example.com",
+                "commit",
+                "--quiet",
+                "-m",
+                message,
+            ],
+        ).unwrap();
+        git(Some(repo), &["rev-parse", "HEAD"]).unwrap()
+    }
+
+    fn pack(repo: &Path, reference: GitReference) -> GitPack {
+        GitPack {
+            url: format!("file://{}", repo.display()),
+            reference,
+            path: None,
+        }
+    }
+
+    #[test]
+    fn it_parses_a_pack_from_toml() {
+        let value = "git = \"file:///gens\"\ntag = \"v1\"\npath = \"web\""
+            .parse::<toml::Value>()
+            .unwrap();
+        assert_eq!(
+            GitPack {
+                url: "file:///gens".to_owned(),
+                reference: GitReference::Tag("v1".to_owned()),
+                path: Some("web".to_owned()),
+            },
+            GitPack::try_from_toml(&value).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_fails_if_a_pack_has_more_than_one_reference() {
+        let value = "git = \"file:///gens\"\ntag = \"v1\"\nbranch = \"main\""
+            .parse::<toml::Value>()
+            .unwrap();
+        assert!(GitPack::try_from_toml(&value).is_err());
+    }
+
+    #[test]
+    fn it_rejects_values_that_git_would_take_for_options() {
+        for toml_str in &[
+            "git = \"--upload-pack=touch pwned\"",
+            "git = \"file:///gens\"\nrev = \"--output=pwned\"",
+            "git = \"file:///gens\"\nbranch = \"-b\"",
+        ] {
+            let value = toml_str.parse::<toml::Value>().unwrap();
+            assert!(GitPack::try_from_toml(&value).is_err(), "{}", toml_str);
+        }
+    }
+
+    #[test]
+    fn it_rejects_a_path_outside_the_repository() {
+        for path in &["../gens", "web/../../gens", "/gens"] {
+            let value = format!("git = \"file:///gens\"\npath = \"{}\"", path)
+                .parse::<toml::Value>()
+                .unwrap();
+            assert!(GitPack::try_from_toml(&value).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn it_rejects_a_locked_commit_that_is_not_a_commit_id() {
+        let home = create_empty_crate("cargo-gen-home").unwrap();
+        let repo = create_empty_crate("cargo-gen-pack").unwrap();
+        let result = pack(repo.path(), GitReference::DefaultBranch)
+            .checkout(home.path(), Some("--output=pwned"));
+        assert!(result.is_err());
+        assert!(!home.path().join("git").exists());
+    }
+
+    #[test]
+    fn it_checks_out_a_tag_of_a_local_repository() {
+        let home = create_empty_crate("cargo-gen-home").unwrap();
+        let repo = create_empty_crate("cargo-gen-pack").unwrap();
+        git(Some(repo.path()), &["init", "--quiet"]).unwrap();
+        create_file(repo.path().join("cargo_generators.yaml"), "- name: v1").unwrap();
+        let tagged_commit = commit_all(repo.path(), "v1");
+        git(Some(repo.path()), &["tag", "v1"]).unwrap();
+        create_file(repo.path().join("cargo_generators.yaml"), "- name: v2").unwrap();
+        commit_all(repo.path(), "v2");
+
+        let (root, commit) = pack(repo.path(), GitReference::Tag("v1".to_owned()))
+            .checkout(home.path(), None)
+            .unwrap();
+        assert_eq!(tagged_commit, commit);
+        assert!(root.starts_with(home.path().join("git/checkouts")));
+        let mut content = String::new();
+        File::open(root.join("cargo_generators.yaml"))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!("- name: v1", content);
+    }
+
+    #[test]
+    fn it_keeps_using_the_locked_commit_of_a_branch() {
+        let home = create_empty_crate("cargo-gen-home").unwrap();
+        let repo = create_empty_crate("cargo-gen-pack").unwrap();
+        git(Some(repo.path()), &["init", "--quiet"]).unwrap();
+        let first_commit = commit_all(repo.path(), "first");
+        let branch = git(Some(repo.path()), &["rev-parse", "--abbrev-ref", "HEAD"]).unwrap();
+        create_file(repo.path().join("cargo_generators.yaml"), "[]").unwrap();
+        commit_all(repo.path(), "second");
+
+        let (_, commit) = pack(repo.path(), GitReference::Branch(branch))
+            .checkout(home.path(), Some(&first_commit))
+            .unwrap();
+        assert_eq!(first_commit, commit);
+    }
+
+    #[test]
+    fn it_fails_on_an_unknown_reference() {
+        let home = create_empty_crate("cargo-gen-home").unwrap();
+        let repo = create_empty_crate("cargo-gen-pack").unwrap();
+        git(Some(repo.path()), &["init", "--quiet"]).unwrap();
+        commit_all(repo.path(), "first");
+        assert!(
+            pack(repo.path(), GitReference::Tag("missing".to_owned()))
+                .checkout(home.path(), None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn it_saves_and_loads_the_lockfile() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let pack = pack(crate_dir.path(), GitReference::DefaultBranch);
+        let mut lock = PackLock::load(crate_dir.path()).unwrap();
+        assert_eq!(None, lock.commit_of(&pack));
+        lock.record(&pack, "abc");
+        lock.save().unwrap();
+        assert_eq!(
+            Some("abc"),
+            PackLock::load(crate_dir.path()).unwrap().commit_of(&pack)
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! A hash that stays the same across Rust releases, for the hashes that are stored on disk or
+//! name directories. `DefaultHasher` makes no such promise.
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// The 64-bit FNV-1a hash of `bytes`, as 16 hex digits.
+pub fn stable_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod hash_test {
+    use super::*;
+
+    #[test]
+    fn it_hashes_with_fnv_1a() {
+        assert_eq!("cbf29ce484222325", stable_hash(b""));
+        assert_eq!("af63dc4c8601ec8c", stable_hash(b"a"));
+        assert_eq!("85944171f73967e8", stable_hash(b"foobar"));
+    }
+}
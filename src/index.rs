@@ -0,0 +1,462 @@
+//! A discovery index cached in the target directory.
+//!
+//! Resolving the dependency graph with `cargo metadata` is the slowest part of finding the
+//! generators. The index remembers which generator manifests were found, together with
+//! fingerprints of everything that could change that answer: the `Cargo.lock` and the manifests
+//! of the local packages. While they all hold the metadata call can be skipped. It also remembers
+//! where the generator packs are checked out, so that a fresh index does not touch git.
+extern crate yaml_rust;
+
+use std::env;
+use std::fs::{self, create_dir_all, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use self::yaml_rust::{Yaml, YamlEmitter, YamlLoader};
+use self::yaml_rust::yaml::Hash as YamlHash;
+use failure::{err_msg, Error};
+use gen::{GeneratorManifest, PackageInfo};
+use hash::stable_hash;
+
+/// Bumped whenever the layout of the index file changes so that stale formats get rebuilt.
+const INDEX_VERSION: i64 = 7;
+
+/// The mtime and hash of a path that does not exist. Watching missing paths lets the index notice
+/// a generator manifest being added to a local package.
+const MISSING: &str = "missing";
+
+#[derive(Debug, PartialEq)]
+struct Fingerprint {
+    path: PathBuf,
+    mtime: String,
+    hash: String,
+}
+
+impl Fingerprint {
+    fn of(path: &Path) -> Result<Fingerprint, Error> {
+        Ok(Fingerprint {
+            path: path.to_path_buf(),
+            mtime: mtime(path)?,
+            hash: content_hash(path)?,
+        })
+    }
+
+    /// A path is unchanged if its mtime is the same. If only the mtime differs (e.g. the file was
+    /// touched or checked out again) the content hash decides.
+    fn is_fresh(&self) -> bool {
+        match mtime(&self.path) {
+            Err(_) => false,
+            Ok(ref mtime) if *mtime == self.mtime => true,
+            Ok(_) => content_hash(&self.path)
+                .map(|hash| hash == self.hash)
+                .unwrap_or(false),
+        }
+    }
+
+    fn to_yaml(&self) -> Yaml {
+        let mut hash = YamlHash::new();
+        hash.insert(
+            Yaml::from_str("path"),
+            Yaml::String(self.path.to_string_lossy().into_owned()),
+        );
+        hash.insert(Yaml::from_str("mtime"), Yaml::String(self.mtime.clone()));
+        hash.insert(Yaml::from_str("hash"), Yaml::String(self.hash.clone()));
+        Yaml::Hash(hash)
+    }
+
+    fn try_from_yaml(yaml: &Yaml) -> Result<Fingerprint, Error> {
+        Ok(Fingerprint {
+            path: PathBuf::from(yaml_str(yaml, "path")?),
+            mtime: yaml_str(yaml, "mtime")?.to_owned(),
+            hash: yaml_str(yaml, "hash")?.to_owned(),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Index {
+    root: PathBuf,
+    pub workspace_root: PathBuf,
+    lockfile_hash: String,
+    fingerprints: Vec<Fingerprint>,
+    pub generator_manifests: Vec<GeneratorManifest>,
+    /// The checkouts of the generator packs, see the `git` module.
+    pub pack_roots: Vec<PathBuf>,
+}
+
+impl Index {
+    /// Records the state of the `watched` paths (the manifests of local packages, including the
+    /// generator manifests they do not have yet) and of the generator manifests found.
+    pub fn build(
+        root_crate_path: &Path,
+        workspace_root: PathBuf,
+        watched: &[PathBuf],
+        generator_manifests: Vec<GeneratorManifest>,
+        pack_roots: Vec<PathBuf>,
+    ) -> Result<Index, Error> {
+        let lockfile = find_lockfile(root_crate_path)
+            .ok_or_else(|| err_msg("Could not find Cargo.lock to key the discovery index on"))?;
+        let mut fingerprints: Vec<Fingerprint> = Vec::new();
+        for path in watched
+            .iter()
+            .chain(generator_manifests.iter().map(|cg_yaml| &cg_yaml.path))
+        {
+            if fingerprints.iter().all(|f| f.path != *path) {
+                fingerprints.push(Fingerprint::of(path)?);
+            }
+        }
+        Ok(Index {
+            root: root_crate_path.to_path_buf(),
+            workspace_root,
+            lockfile_hash: content_hash(&lockfile)?,
+            fingerprints,
+            generator_manifests,
+            pack_roots,
+        })
+    }
+
+    /// Loads the index of `root_crate_path` unless it is missing, unreadable or outdated.
+    pub fn load_fresh(root_crate_path: &Path) -> Option<Index> {
+        let index = index_path(root_crate_path)
+            .and_then(|path| Index::load(&path).ok())?;
+        if index.is_fresh(root_crate_path) {
+            Some(index)
+        } else {
+            None
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let path = index_path(&self.root)
+            .ok_or_else(|| err_msg("Could not find Cargo.lock to key the discovery index on"))?;
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+        let mut yaml_str = String::new();
+        YamlEmitter::new(&mut yaml_str).dump(&self.to_yaml())?;
+        File::create(&path)?.write_all(yaml_str.as_bytes())?;
+        Ok(())
+    }
+
+    fn load(path: &Path) -> Result<Index, Error> {
+        let mut yaml_str = String::new();
+        File::open(path)?.read_to_string(&mut yaml_str)?;
+        let yamls = YamlLoader::load_from_str(&yaml_str)?;
+        let yaml = yamls
+            .first()
+            .ok_or_else(|| err_msg("The discovery index is empty"))?;
+        Index::try_from_yaml(yaml)
+    }
+
+    fn is_fresh(&self, root_crate_path: &Path) -> bool {
+        self.root == root_crate_path
+            && find_lockfile(root_crate_path)
+                .and_then(|lockfile| content_hash(&lockfile).ok())
+                .map(|hash| hash == self.lockfile_hash)
+                .unwrap_or(false)
+            && self.fingerprints.iter().all(|f| f.is_fresh())
+    }
+
+    fn to_yaml(&self) -> Yaml {
+        let mut hash = YamlHash::new();
+        hash.insert(Yaml::from_str("version"), Yaml::Integer(INDEX_VERSION));
+        hash.insert(
+            Yaml::from_str("root"),
+            Yaml::String(self.root.to_string_lossy().into_owned()),
+        );
+        hash.insert(
+            Yaml::from_str("workspace-root"),
+            Yaml::String(self.workspace_root.to_string_lossy().into_owned()),
+        );
+        hash.insert(
+            Yaml::from_str("lockfile-hash"),
+            Yaml::String(self.lockfile_hash.clone()),
+        );
+        hash.insert(
+            Yaml::from_str("fingerprints"),
+            Yaml::Array(self.fingerprints.iter().map(|f| f.to_yaml()).collect()),
+        );
+        hash.insert(
+            Yaml::from_str("generator-manifests"),
+            Yaml::Array(
+                self.generator_manifests
+                    .iter()
+                    .map(generator_manifest_to_yaml)
+                    .collect(),
+            ),
+        );
+        hash.insert(
+            Yaml::from_str("pack-roots"),
+            Yaml::Array(
+                self.pack_roots
+                    .iter()
+                    .map(|root| Yaml::String(root.to_string_lossy().into_owned()))
+                    .collect(),
+            ),
+        );
+        Yaml::Hash(hash)
+    }
+
+    fn try_from_yaml(yaml: &Yaml) -> Result<Index, Error> {
+        if yaml["version"].as_i64() != Some(INDEX_VERSION) {
+            bail!("The discovery index has an unsupported version");
+        }
+        let fingerprints = yaml_vec(yaml, "fingerprints")?
+            .iter()
+            .map(Fingerprint::try_from_yaml)
+            .collect::<Result<Vec<_>, _>>()?;
+        let generator_manifests = yaml_vec(yaml, "generator-manifests")?
+            .iter()
+            .map(generator_manifest_from_yaml)
+            .collect::<Result<Vec<_>, _>>()?;
+        let pack_roots = yaml_vec(yaml, "pack-roots")?
+            .iter()
+            .map(|root| {
+                root.as_str()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| err_msg("A pack root is not a string"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Index {
+            root: PathBuf::from(yaml_str(yaml, "root")?),
+            workspace_root: PathBuf::from(yaml_str(yaml, "workspace-root")?),
+            lockfile_hash: yaml_str(yaml, "lockfile-hash")?.to_owned(),
+            fingerprints,
+            generator_manifests,
+            pack_roots,
+        })
+    }
+}
+
+fn generator_manifest_to_yaml(cg_yaml: &GeneratorManifest) -> Yaml {
+    let mut hash = YamlHash::new();
+    hash.insert(
+        Yaml::from_str("path"),
+        Yaml::String(cg_yaml.path.to_string_lossy().into_owned()),
+    );
+    hash.insert(
+        Yaml::from_str("package-id"),
+        Yaml::String(cg_yaml.package.id.clone()),
+    );
+    hash.insert(
+        Yaml::from_str("package-name"),
+        Yaml::String(cg_yaml.package.name.clone()),
+    );
+    hash.insert(
+        Yaml::from_str("package-version"),
+        Yaml::String(cg_yaml.package.version.clone()),
+    );
+    hash.insert(
+        Yaml::from_str("dependency-path"),
+        Yaml::Array(
+            cg_yaml
+                .package
+                .dependency_path
+                .iter()
+                .map(|name| Yaml::String(name.clone()))
+                .collect(),
+        ),
+    );
+    hash.insert(
+        Yaml::from_str("features"),
+        match cg_yaml.features {
+            Some(ref features) => Yaml::Array(
+                features
+                    .iter()
+                    .map(|feature| Yaml::String(feature.clone()))
+                    .collect(),
+            ),
+            None => Yaml::Null,
+        },
+    );
+    Yaml::Hash(hash)
+}
+
+fn generator_manifest_from_yaml(yaml: &Yaml) -> Result<GeneratorManifest, Error> {
+    let features = if yaml["features"].is_null() {
+        None
+    } else {
+        Some(
+            yaml_vec(yaml, "features")?
+                .iter()
+                .map(|feature| {
+                    feature
+                        .as_str()
+                        .map(|f| f.to_owned())
+                        .ok_or_else(|| err_msg("A package feature is not a string"))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        )
+    };
+    Ok(GeneratorManifest {
+        path: PathBuf::from(yaml_str(yaml, "path")?),
+        package: PackageInfo {
+            id: yaml_str(yaml, "package-id")?.to_owned(),
+            name: yaml_str(yaml, "package-name")?.to_owned(),
+            version: yaml_str(yaml, "package-version")?.to_owned(),
+            dependency_path: yaml_vec(yaml, "dependency-path")?
+                .iter()
+                .map(|name| {
+                    name.as_str()
+                        .map(|n| n.to_owned())
+                        .ok_or_else(|| err_msg("A package name is not a string"))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        },
+        features,
+    })
+}
+
+/// The index lives in `cargo-gen/` of the target directory, one file per root crate.
+pub fn index_path(root_crate_path: &Path) -> Option<PathBuf> {
+    let hash = stable_hash(root_crate_path.to_string_lossy().as_bytes());
+    Some(
+        target_dir(root_crate_path)?
+            .join("cargo-gen")
+            .join(format!("index-{}.yaml", hash)),
+    )
+}
+
+/// The target directory is located without asking cargo (that is the call the index is trying to
+/// avoid): `CARGO_TARGET_DIR` if set, otherwise `target/` next to the `Cargo.lock`.
+pub fn target_dir(root_crate_path: &Path) -> Option<PathBuf> {
+    match env::var_os("CARGO_TARGET_DIR") {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => Some(find_lockfile(root_crate_path)?.parent()?.join("target")),
+    }
+}
+
+/// Finds the `Cargo.lock` of the workspace `root_crate_path` belongs to.
+pub fn find_lockfile(root_crate_path: &Path) -> Option<PathBuf> {
+    root_crate_path
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.is_file())
+}
+
+/// The directory of the `Cargo.lock`, `root_crate_path` itself if there is none yet.
+pub fn workspace_dir(root_crate_path: &Path) -> PathBuf {
+    find_lockfile(root_crate_path)
+        .and_then(|lockfile| lockfile.parent().map(|dir| dir.to_path_buf()))
+        .unwrap_or_else(|| root_crate_path.to_path_buf())
+}
+
+fn mtime(path: &Path) -> Result<String, Error> {
+    if !path.exists() {
+        return Ok(MISSING.to_owned());
+    }
+    let since_epoch = fs::metadata(path)?.modified()?.duration_since(UNIX_EPOCH)?;
+    Ok(format!(
+        "{}.{:09}",
+        since_epoch.as_secs(),
+        since_epoch.subsec_nanos()
+    ))
+}
+
+fn content_hash(path: &Path) -> Result<String, Error> {
+    if !path.exists() {
+        return Ok(MISSING.to_owned());
+    }
+    let mut content = Vec::new();
+    File::open(path)?.read_to_end(&mut content)?;
+    Ok(stable_hash(&content))
+}
+
+fn yaml_str<'a>(yaml: &'a Yaml, key: &str) -> Result<&'a str, Error> {
+    yaml[key]
+        .as_str()
+        .ok_or_else(|| format_err!("The discovery index entry {} is not a string", key))
+}
+
+fn yaml_vec<'a>(yaml: &'a Yaml, key: &str) -> Result<&'a Vec<Yaml>, Error> {
+    yaml[key]
+        .as_vec()
+        .ok_or_else(|| format_err!("The discovery index entry {} is not an array", key))
+}
+
+#[cfg(test)]
+mod index_test {
+    extern crate cargo_gen_helpers;
+
+    use self::cargo_gen_helpers::test_helpers::create_empty_crate;
+    use self::cargo_gen_helpers::{create_file, modify_file};
+    use super::*;
+
+    fn build_index(root: &Path) -> Index {
+        create_file(root.join("Cargo.lock"), "# lockfile").unwrap();
+        let yaml_path = root.join("cargo_generators.yaml");
+        create_file(&yaml_path, "- name: root.gen\n  factory: f").unwrap();
+        let member_yaml_path = root.join("member/cargo_generators.yaml");
+        Index::build(
+            root,
+            root.to_path_buf(),
+            &[root.join("Cargo.toml"), yaml_path.clone(), member_yaml_path],
+            vec![GeneratorManifest {
+                path: yaml_path,
+                package: PackageInfo {
+                    id: "cargo-gen-test 0.1.0 (path+file:///cargo-gen-test)".to_owned(),
+                    name: "cargo-gen-test".to_owned(),
+                    version: "0.1.0".to_owned(),
+                    dependency_path: vec!["cargo-gen-test".to_owned()],
+                },
+                features: Some(vec!["default".to_owned()]),
+            }],
+            vec![root.join("pack")],
+        ).unwrap()
+    }
+
+    #[test]
+    fn it_saves_and_loads_a_fresh_index() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let index = build_index(crate_dir.path());
+        index.save().unwrap();
+        assert_eq!(Some(index), Index::load_fresh(crate_dir.path()));
+    }
+
+    #[test]
+    fn it_does_not_load_a_missing_index() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        assert_eq!(None, Index::load_fresh(crate_dir.path()));
+    }
+
+    #[test]
+    fn it_invalidates_the_index_when_the_lockfile_changes() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        build_index(crate_dir.path()).save().unwrap();
+        create_file(crate_dir.path().join("Cargo.lock"), "# another lockfile").unwrap();
+        assert_eq!(None, Index::load_fresh(crate_dir.path()));
+    }
+
+    #[test]
+    fn it_invalidates_the_index_when_a_manifest_changes() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        build_index(crate_dir.path()).save().unwrap();
+        modify_file(crate_dir.path().join("Cargo.toml"), |contents| {
+            Ok(Some(format!("{}\n# changed\n", contents)))
+        }).unwrap();
+        assert_eq!(None, Index::load_fresh(crate_dir.path()));
+    }
+
+    #[test]
+    fn it_invalidates_the_index_when_a_generator_manifest_appears() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        build_index(crate_dir.path()).save().unwrap();
+        create_file(
+            crate_dir.path().join("member/cargo_generators.yaml"),
+            "- name: member.gen\n  factory: f",
+        ).unwrap();
+        assert_eq!(None, Index::load_fresh(crate_dir.path()));
+    }
+
+    #[test]
+    fn it_keeps_the_index_when_a_manifest_is_rewritten_with_the_same_content() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let index = build_index(crate_dir.path());
+        index.save().unwrap();
+        create_file(
+            crate_dir.path().join("cargo_generators.yaml"),
+            "- name: root.gen\n  factory: f",
+        ).unwrap();
+        assert_eq!(Some(index), Index::load_fresh(crate_dir.path()));
+    }
+}
@@ -0,0 +1,238 @@
+//! Interactive mode of `cargo gen`: picks a generator and asks for its arguments. Reads the
+//! answers a line at a time so that they can come from a pipe as well as from a terminal.
+use std::io::{BufRead, Write};
+use failure::Error;
+use gen::{Generator, GeneratorArg};
+
+/// Lists the generators and asks for one, by its number in the list or by its name or alias.
+pub fn pick_generator<'a, R, W>(
+    generators: &[&'a Generator],
+    input: &mut R,
+    output: &mut W,
+) -> Result<&'a Generator, Error>
+where
+    R: BufRead,
+    W: Write,
+{
+    if generators.is_empty() {
+        bail!("There are no generators to pick from");
+    }
+    let width = generators
+        .iter()
+        .map(|generator| generator.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (i, generator) in generators.iter().enumerate() {
+        let about = generator.about().unwrap_or("");
+        let line = format!("{:>3}) {:<width$}  {}", i + 1, generator.name, about, width = width);
+        writeln!(output, "{}", line.trim_end())?;
+    }
+    loop {
+        let answer = ask(input, output, &format!("Generator [1-{}]", generators.len()))?;
+        let picked = match answer.parse::<usize>() {
+            Ok(number) if number >= 1 => generators.get(number - 1),
+            Ok(_) => None,
+            Err(_) => generators
+                .iter()
+                .find(|generator| generator.is_known_as(&answer)),
+        };
+        match picked {
+            Some(generator) => return Ok(generator),
+            None => writeln!(output, "No generator {}, pick one of the list", answer)?,
+        }
+    }
+}
+
+/// Asks for the value of each argument of `generator` and returns the arguments to run it with.
+/// Empty answers leave optional arguments out, so that the generator applies its defaults. The
+/// positional arguments come first, in the order clap numbers them, and an empty answer for one
+/// ends them: clap would take the value of the next one for it.
+pub fn prompt_args<R, W>(
+    generator: &Generator,
+    input: &mut R,
+    output: &mut W,
+) -> Result<Vec<String>, Error>
+where
+    R: BufRead,
+    W: Write,
+{
+    let (mut positionals, options): (Vec<_>, Vec<_>) =
+        generator.args().iter().partition(|arg| arg.is_positional());
+    // clap numbers the positional arguments without an index in the order they are declared.
+    positionals.sort_by_key(|arg| arg.index.unwrap_or(u64::MAX));
+    let mut positional_values = Vec::new();
+    for arg in positionals {
+        let values = prompt_arg(arg, input, output)?;
+        if values.is_empty() {
+            break;
+        }
+        positional_values.extend(values);
+    }
+    let mut args = Vec::new();
+    for arg in options {
+        let values = prompt_arg(arg, input, output)?;
+        let flag = match arg.long {
+            Some(ref long) => format!("--{}", long),
+            None => format!("-{}", arg.short.as_deref().unwrap_or_default()),
+        };
+        if !arg.takes_value() {
+            if values.first().map_or(false, |value| value == "y") {
+                args.push(flag);
+            }
+            continue;
+        }
+        for value in values {
+            args.push(flag.clone());
+            args.push(value);
+        }
+    }
+    args.extend(positional_values);
+    Ok(args)
+}
+
+/// The values given for `arg`, `y` or nothing for a flag.
+fn prompt_arg<R, W>(
+    arg: &GeneratorArg,
+    input: &mut R,
+    output: &mut W,
+) -> Result<Vec<String>, Error>
+where
+    R: BufRead,
+    W: Write,
+{
+    let mut question = arg
+        .prompt
+        .clone()
+        .or_else(|| arg.help.clone())
+        .unwrap_or_else(|| arg.name.clone());
+    if !arg.takes_value() {
+        question.push_str(" [y/N]");
+        let answer = ask(input, output, &question)?.to_lowercase();
+        return Ok(if answer == "y" || answer == "yes" {
+            vec!["y".to_owned()]
+        } else {
+            vec![]
+        });
+    }
+    if !arg.possible_values.is_empty() {
+        question.push_str(&format!(" [{}]", arg.possible_values.join("/")));
+    }
+    if let Some(ref default_value) = arg.default_value {
+        question.push_str(&format!(" (default: {})", default_value));
+    }
+    if arg.multiple {
+        question.push_str(" (separated by spaces)");
+    }
+    loop {
+        let answer = ask(input, output, &question)?;
+        let values = if arg.multiple {
+            answer.split_whitespace().map(|value| value.to_owned()).collect()
+        } else if answer.is_empty() {
+            vec![]
+        } else {
+            vec![answer]
+        };
+        if values.is_empty() && arg.required && arg.default_value.is_none() {
+            writeln!(output, "{} is required", arg.name)?;
+            continue;
+        }
+        let invalid = values.iter().find(|value| {
+            !arg.possible_values.is_empty() && !arg.possible_values.contains(value)
+        });
+        match invalid {
+            Some(value) => writeln!(
+                output,
+                "{} is not one of {}",
+                value,
+                arg.possible_values.join(", ")
+            )?,
+            None => return Ok(values),
+        }
+    }
+}
+
+/// Writes the question and reads a line of answer, without the surrounding whitespace.
+fn ask<R, W>(input: &mut R, output: &mut W, question: &str) -> Result<String, Error>
+where
+    R: BufRead,
+    W: Write,
+{
+    write!(output, "{}: ", question)?;
+    output.flush()?;
+    let mut answer = String::new();
+    if input.read_line(&mut answer)? == 0 {
+        bail!("No answer to: {}", question);
+    }
+    Ok(answer.trim().to_owned())
+}
+
+#[cfg(test)]
+mod interactive_test {
+    extern crate yaml_rust;
+
+    use self::yaml_rust::YamlLoader;
+    use super::*;
+
+    fn generator(yaml: &str) -> Generator {
+        Generator::try_from_yaml(&YamlLoader::load_from_str(yaml).unwrap()[0]).unwrap()
+    }
+
+    fn app() -> Generator {
+        generator(
+            "name: web.app\nfactory: f\nabout: An app\nargs:\n  \
+             - NAME:\n      help: The name\n      prompt: What is the app called?\n      \
+             required: true\n      index: 1\n  \
+             - db:\n      long: db\n      possible_values: [postgres, sqlite]\n  \
+             - force:\n      short: f\n  \
+             - KIND:\n      default_value: site\n      index: 2",
+        )
+    }
+
+    #[test]
+    fn it_picks_a_generator_by_number_or_name() {
+        let (app, model) = (app(), generator("name: web.model\nfactory: f"));
+        let generators = [&app, &model];
+        let mut output = Vec::new();
+        let picked = pick_generator(&generators, &mut &b"3\nweb.model\n"[..], &mut output);
+        assert_eq!("web.model", picked.unwrap().name);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("  1) web.app    An app\n  2) web.model\nGenerator [1-2]: "));
+        assert!(output.contains("No generator 3, pick one of the list\n"));
+
+        let picked = pick_generator(&generators, &mut &b"1\n"[..], &mut Vec::new());
+        assert_eq!("web.app", picked.unwrap().name);
+    }
+
+    #[test]
+    fn it_prompts_for_each_argument() {
+        let mut output = Vec::new();
+        let answers = b"\nblog\npage\nmysql\nsqlite\nyes\n";
+        let args = prompt_args(&app(), &mut &answers[..], &mut output);
+        assert_eq!(vec!["--db", "sqlite", "-f", "blog", "page"], args.unwrap());
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("What is the app called?: NAME is required\n"));
+        assert!(output.contains("KIND (default: site): db [postgres/sqlite]: "));
+        assert!(output.contains("mysql is not one of postgres, sqlite\n"));
+        assert!(output.ends_with("force [y/N]: "));
+    }
+
+    #[test]
+    fn it_stops_prompting_for_positionals_after_an_empty_one() {
+        let generator = generator(
+            "name: a\nfactory: f\nargs:\n  - SECOND:\n      index: 2\n  - FIRST:\n      index: 1",
+        );
+        let mut output = Vec::new();
+        let args = prompt_args(&generator, &mut &b"\n"[..], &mut output);
+        assert!(args.unwrap().is_empty());
+        assert_eq!("FIRST: ", String::from_utf8(output).unwrap());
+
+        let args = prompt_args(&generator, &mut &b"one\ntwo\n"[..], &mut Vec::new());
+        assert_eq!(vec!["one", "two"], args.unwrap());
+    }
+
+    #[test]
+    fn it_fails_when_the_answers_run_out() {
+        let err = prompt_args(&app(), &mut &b"blog\n"[..], &mut Vec::new()).unwrap_err();
+        assert_eq!("No answer to: KIND (default: site)", err.to_string());
+    }
+}
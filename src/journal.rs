@@ -0,0 +1,405 @@
+//! Undoing the last run of a generator.
+//!
+//! Before a generator runs, the size and the modification time of the files of the workspace are
+//! taken into a snapshot, the target directories and the hidden directories aside. While it runs,
+//! the helpers back the files up before they first write or remove them, see
+//! `cargo_gen_helpers::create_file`. After it ran, the files it created, changed or removed are
+//! recorded in `.cargo-gen/undo`, together with the content the changed and removed ones had
+//! before. `cargo gen undo` puts that content back and removes the created files. Only the last
+//! run is recorded.
+extern crate toml;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, create_dir_all, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+use failure::Error;
+use tempdir::TempDir;
+use hash::stable_hash;
+use index::{target_dir, workspace_dir};
+
+/// Where the last run is recorded, relative to the workspace root.
+pub const UNDO_DIR: &str = ".cargo-gen/undo";
+/// Where the backups are made and the record is put together, next to `UNDO_DIR` so that they
+/// can be moved there.
+const WORK_DIR: &str = ".cargo-gen";
+const JOURNAL: &str = "journal.toml";
+/// The content the changed and removed files had, by their path in the workspace.
+const BACKUP_DIR: &str = "files";
+
+/// The files of the workspace before a generator runs, and the directory the generator backs
+/// them up to. The directory is removed when dropped.
+#[derive(Debug)]
+pub struct Snapshot {
+    root: PathBuf,
+    files: BTreeMap<PathBuf, Stamp>,
+    backup_dir: TempDir,
+}
+
+/// What tells a file changed without reading it.
+#[derive(Debug, PartialEq)]
+struct Stamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// What a run changed, by path relative to the workspace root.
+#[derive(Debug, Default, PartialEq)]
+pub struct Journal {
+    pub generator: String,
+    pub created: Vec<PathBuf>,
+    pub changed: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    /// The content hash of the created and changed files after the run, so that undoing does not
+    /// lose the edits made since.
+    hashes: BTreeMap<PathBuf, String>,
+}
+
+impl Snapshot {
+    /// Takes the files of the workspace `root_crate_path` belongs to, and creates the backup
+    /// directory.
+    pub fn take(root_crate_path: &Path) -> Result<Snapshot, Error> {
+        let root = workspace_dir(root_crate_path);
+        let files = stamp_files(&root)?;
+        create_dir_all(root.join(WORK_DIR))?;
+        let backup_dir = TempDir::new_in(root.join(WORK_DIR), "backup")?;
+        Ok(Snapshot {
+            root,
+            files,
+            backup_dir,
+        })
+    }
+
+    /// The directory the generator backs the files up to, see `cargo_gen_helpers`.
+    pub fn backup_dir(&self) -> &Path {
+        self.backup_dir.path()
+    }
+
+    /// Compares the workspace with the snapshot and records the changes for `undo`, over the
+    /// changes recorded before. Fails if a file changed or was removed without a backup, e.g.
+    /// because the generator does not write it with the helpers.
+    pub fn record(&self, generator: &str) -> Result<Journal, Error> {
+        let files = stamp_files(&self.root)?;
+        let mut journal = Journal {
+            generator: generator.to_owned(),
+            ..Journal::default()
+        };
+        for path in files.keys().filter(|path| !self.files.contains_key(*path)) {
+            journal.created.push(path.clone());
+        }
+        for (path, stamp) in &self.files {
+            let backup = self.backup_path(path);
+            let removed = !files.contains_key(path);
+            if !backup.is_file() {
+                if removed || files.get(path) != Some(stamp) {
+                    bail!("{} changed without a backup", path.display());
+                }
+                continue;
+            }
+            if removed {
+                journal.removed.push(path.clone());
+            } else if fs::read(&backup)? != fs::read(self.root.join(path))? {
+                journal.changed.push(path.clone());
+            }
+        }
+        for path in journal.created.iter().chain(&journal.changed) {
+            let content = fs::read(self.root.join(path))?;
+            journal.hashes.insert(path.clone(), stable_hash(&content));
+        }
+
+        // The new record replaces the old one once it is complete.
+        let undo_dir = self.root.join(UNDO_DIR);
+        let new_undo_dir = TempDir::new_in(self.root.join(WORK_DIR), "undo")?;
+        for path in journal.changed.iter().chain(&journal.removed) {
+            let backup = new_undo_dir.path().join(BACKUP_DIR).join(path);
+            if let Some(dir) = backup.parent() {
+                create_dir_all(dir)?;
+            }
+            fs::rename(self.backup_path(path), backup)?;
+        }
+        File::create(new_undo_dir.path().join(JOURNAL))?
+            .write_all(journal.to_toml().as_bytes())?;
+        if undo_dir.exists() {
+            fs::remove_dir_all(&undo_dir)?;
+        }
+        fs::rename(new_undo_dir.into_path(), &undo_dir)?;
+        Ok(journal)
+    }
+
+    /// Where the helpers back up the file at `path`, relative to the workspace root: under its
+    /// absolute path without the root.
+    fn backup_path(&self, path: &Path) -> PathBuf {
+        let mut backup = self.backup_dir.path().to_path_buf();
+        for component in self.root.join(path).components() {
+            if let Component::Normal(name) = component {
+                backup.push(name);
+            }
+        }
+        backup
+    }
+}
+
+impl Journal {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+
+    fn to_toml(&self) -> String {
+        let paths = |paths: &[PathBuf]| {
+            toml::Value::Array(
+                paths
+                    .iter()
+                    .map(|path| toml::Value::String(path.to_string_lossy().into_owned()))
+                    .collect(),
+            )
+        };
+        let mut table = toml::value::Table::new();
+        table.insert(
+            "generator".to_owned(),
+            toml::Value::String(self.generator.clone()),
+        );
+        table.insert("created".to_owned(), paths(&self.created));
+        table.insert("changed".to_owned(), paths(&self.changed));
+        table.insert("removed".to_owned(), paths(&self.removed));
+        table.insert(
+            "hashes".to_owned(),
+            toml::Value::Table(
+                self.hashes
+                    .iter()
+                    .map(|(path, hash)| {
+                        (path.to_string_lossy().into_owned(), toml::Value::String(hash.clone()))
+                    })
+                    .collect(),
+            ),
+        );
+        format!(
+            "# This file is generated by cargo-gen. `cargo gen undo` undoes the run it \
+             records.\n{}",
+            toml::Value::Table(table)
+        )
+    }
+
+    fn read(path: &Path) -> Result<Journal, Error> {
+        let mut toml_str = String::new();
+        File::open(path)?.read_to_string(&mut toml_str)?;
+        let value = toml_str
+            .parse::<toml::Value>()
+            .map_err(|e| format_err!("{} is not valid: {}", path.display(), e))?;
+        let invalid = |key: &str| format_err!("The {} of {} are not valid", key, path.display());
+        let paths = |key: &str| {
+            value
+                .get(key)
+                .and_then(|paths| paths.as_array())
+                .and_then(|paths| {
+                    paths
+                        .iter()
+                        .map(|path| path.as_str().map(PathBuf::from))
+                        .collect::<Option<Vec<_>>>()
+                })
+                .ok_or_else(|| invalid(key))
+        };
+        let hashes = value
+            .get("hashes")
+            .and_then(|hashes| hashes.as_table())
+            .and_then(|hashes| {
+                hashes
+                    .iter()
+                    .map(|(path, hash)| Some((PathBuf::from(path), hash.as_str()?.to_owned())))
+                    .collect::<Option<BTreeMap<_, _>>>()
+            })
+            .ok_or_else(|| invalid("hashes"))?;
+        Ok(Journal {
+            generator: value
+                .get("generator")
+                .and_then(|generator| generator.as_str())
+                .map(|generator| generator.to_owned())
+                .ok_or_else(|| invalid("generator"))?,
+            created: paths("created")?,
+            changed: paths("changed")?,
+            removed: paths("removed")?,
+            hashes,
+        })
+    }
+}
+
+/// Undoes the last run recorded in the workspace `root_crate_path` belongs to. Refuses to if the
+/// files of the run changed since, unless `force` is set.
+pub fn undo(root_crate_path: &Path, force: bool) -> Result<Journal, Error> {
+    let root = workspace_dir(root_crate_path);
+    let undo_dir = root.join(UNDO_DIR);
+    let journal_path = undo_dir.join(JOURNAL);
+    if !journal_path.is_file() {
+        bail!("There is no generator run to undo in {}", root.display());
+    }
+    let journal = Journal::read(&journal_path)?;
+    if !force {
+        let edited = journal
+            .created
+            .iter()
+            .chain(&journal.changed)
+            .filter(|path| {
+                let hash = fs::read(root.join(path)).map(|content| stable_hash(&content));
+                hash.ok().as_ref() != journal.hashes.get(*path)
+            })
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>();
+        if !edited.is_empty() {
+            bail!(
+                "{} changed since {} ran, undoing would lose the changes: {}",
+                if edited.len() == 1 { "A file" } else { "Files" },
+                journal.generator,
+                edited.join(", ")
+            );
+        }
+    }
+    for path in &journal.created {
+        match fs::remove_file(root.join(path)) {
+            Err(ref e) if e.kind() != io::ErrorKind::NotFound => {
+                bail!("{} could not be removed: {}", path.display(), e)
+            }
+            _ => {}
+        }
+    }
+    for path in journal.changed.iter().chain(&journal.removed) {
+        let target = root.join(path);
+        if let Some(dir) = target.parent() {
+            create_dir_all(dir)?;
+        }
+        fs::copy(undo_dir.join(BACKUP_DIR).join(path), &target)
+            .map_err(|e| format_err!("{} could not be restored: {}", path.display(), e))?;
+    }
+    fs::remove_dir_all(&undo_dir)?;
+    Ok(journal)
+}
+
+/// The files under `root` by relative path, without the hidden directories nor the target
+/// directories: the one of the workspace, `CARGO_TARGET_DIR` included, and the `target` of every
+/// package.
+fn stamp_files(root: &Path) -> Result<BTreeMap<PathBuf, Stamp>, Error> {
+    // A relative `CARGO_TARGET_DIR` is relative to the current directory.
+    let current_dir = env::current_dir()?;
+    let workspace_target = target_dir(root).map(|dir| current_dir.join(dir));
+    let mut files = BTreeMap::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                let name = entry.file_name();
+                let hidden = name.to_string_lossy().starts_with('.');
+                let target = Some(&path) == workspace_target.as_ref()
+                    || (name == "target" && dir.join("Cargo.toml").is_file());
+                if !hidden && !target {
+                    dirs.push(path);
+                }
+            } else if file_type.is_file() {
+                let metadata = entry.metadata()?;
+                let stamp = Stamp {
+                    len: metadata.len(),
+                    modified: metadata.modified().ok(),
+                };
+                files.insert(path.strip_prefix(root)?.to_path_buf(), stamp);
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod journal_test {
+    extern crate cargo_gen_helpers;
+
+    use self::cargo_gen_helpers::create_file;
+    use self::cargo_gen_helpers::test_helpers::create_empty_crate;
+    use super::*;
+
+    fn content(path: &Path) -> String {
+        String::from_utf8(fs::read(path).unwrap()).unwrap()
+    }
+
+    /// Backs the file up as the helpers do before the generator writes or removes it.
+    fn back_up(snapshot: &Snapshot, path: &str) {
+        let backup = snapshot.backup_path(Path::new(path));
+        if !backup.exists() && snapshot.root.join(path).is_file() {
+            create_dir_all(backup.parent().unwrap()).unwrap();
+            fs::copy(snapshot.root.join(path), backup).unwrap();
+        }
+    }
+
+    fn write(snapshot: &Snapshot, path: &str, content: &str) {
+        back_up(snapshot, path);
+        create_file(snapshot.root.join(path), content).unwrap();
+    }
+
+    fn dirs(dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().path()).collect()
+    }
+
+    #[test]
+    fn it_undoes_the_changes_of_the_last_run() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let root = crate_dir.path();
+        create_file(root.join("Cargo.lock"), "").unwrap();
+        create_file(root.join("src/lib.rs"), "mod a;").unwrap();
+        create_file(root.join("src/old.rs"), "old").unwrap();
+        create_file(root.join("target/debug/build"), "").unwrap();
+        create_file(root.join("member/Cargo.toml"), "[package]").unwrap();
+        let snapshot = Snapshot::take(&root.join("src")).unwrap();
+
+        write(&snapshot, "src/lib.rs", "mod a;\nmod app;");
+        write(&snapshot, "src/app/mod.rs", "app");
+        back_up(&snapshot, "src/old.rs");
+        fs::remove_file(root.join("src/old.rs")).unwrap();
+        create_file(root.join("target/debug/runner"), "").unwrap();
+        create_file(root.join("member/target/debug/member"), "").unwrap();
+        let journal = snapshot.record("web.app").unwrap();
+        assert_eq!(vec![PathBuf::from("src/app/mod.rs")], journal.created);
+        assert_eq!(vec![PathBuf::from("src/lib.rs")], journal.changed);
+        assert_eq!(vec![PathBuf::from("src/old.rs")], journal.removed);
+        drop(snapshot);
+        assert_eq!(vec![root.join(UNDO_DIR)], dirs(&root.join(WORK_DIR)));
+
+        assert_eq!(journal, undo(root, false).unwrap());
+        assert_eq!("mod a;", content(&root.join("src/lib.rs")));
+        assert_eq!("old", content(&root.join("src/old.rs")));
+        assert!(!root.join("src/app/mod.rs").exists());
+        assert!(undo(root, false).is_err());
+    }
+
+    #[test]
+    fn it_keeps_the_changes_made_since_the_run() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let root = crate_dir.path();
+        create_file(root.join("Cargo.lock"), "").unwrap();
+        let snapshot = Snapshot::take(root).unwrap();
+        write(&snapshot, "src/app.rs", "app");
+        snapshot.record("web.app").unwrap();
+        create_file(root.join("src/app.rs"), "app, edited").unwrap();
+
+        let err = undo(root, false).unwrap_err();
+        assert_eq!(
+            "A file changed since web.app ran, undoing would lose the changes: src/app.rs",
+            err.to_string()
+        );
+        undo(root, true).unwrap();
+        assert!(!root.join("src/app.rs").exists());
+    }
+
+    #[test]
+    fn it_fails_to_record_a_change_without_a_backup() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let root = crate_dir.path();
+        create_file(root.join("Cargo.lock"), "").unwrap();
+        create_file(root.join("src/old.rs"), "old").unwrap();
+        let snapshot = Snapshot::take(root).unwrap();
+        fs::remove_file(root.join("src/old.rs")).unwrap();
+
+        let err = snapshot.record("web.app").unwrap_err();
+        assert_eq!("src/old.rs changed without a backup", err.to_string());
+        assert!(!root.join(UNDO_DIR).exists());
+    }
+}
@@ -1,5 +1,26 @@
 #[macro_use]
 extern crate failure;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_json;
+extern crate tempdir;
 
+pub mod answers;
+pub mod arg_values;
+pub mod catalog;
 pub mod cmd_args;
+pub mod completion;
+pub mod config;
+pub mod docs;
+pub mod dry_run;
 pub mod gen;
+pub mod git;
+mod hash;
+mod index;
+pub mod interactive;
+pub mod journal;
+pub mod listing;
+pub mod roots;
+pub mod runner;
+pub mod search;
@@ -0,0 +1,590 @@
+//! Renders the generators for `cargo gen list`.
+use std::collections::BTreeMap;
+use std::path::Path;
+use failure::Error;
+use config::ArgDefault;
+use gen::{Generator, GeneratorArg, PackageInfo};
+use serde_json::Value;
+
+const HEADERS: [&str; 4] = ["GENERATOR", "ABOUT", "VERSION", "PACKAGE"];
+const GAP: &str = "  ";
+/// The about column is never squeezed below this, the terminal wraps the line instead.
+const MIN_ABOUT_WIDTH: usize = 20;
+
+/// Renders the generators as a table with a row per generator. Generators without a category come
+/// first, the others are grouped under their category. The about column is shortened to fit in
+/// `width`, if given. `long` adds the arguments of each generator below its row.
+pub fn table(generators: &[&Generator], width: Option<usize>, long: bool) -> String {
+    highlighted_table(generators, width, long, &|_, cell| cell.to_owned())
+}
+
+/// The columns of the table that `highlighted_table` highlights.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Column {
+    Id,
+    About,
+}
+
+/// Same as `table` but passes the id and about cells through `highlight`, which may add terminal
+/// escape codes.
+pub fn highlighted_table(
+    generators: &[&Generator],
+    width: Option<usize>,
+    long: bool,
+    highlight: &dyn Fn(Column, &str) -> String,
+) -> String {
+    let mut categories = BTreeMap::new();
+    for generator in generators {
+        categories
+            .entry(generator.category())
+            .or_insert_with(Vec::new)
+            .push(*generator);
+    }
+    let rows = categories
+        .iter()
+        .flat_map(|(category, generators)| {
+            let indent = if category.is_some() { GAP } else { "" };
+            generators
+                .iter()
+                .map(move |generator| (*generator, row(generator, indent)))
+        })
+        .collect::<Vec<_>>();
+
+    let mut widths = [0; 4];
+    for cells in Some(&HEADERS.map(|header| header.to_owned()))
+        .into_iter()
+        .chain(rows.iter().map(|(_, cells)| cells))
+    {
+        for (width, cell) in widths.iter_mut().zip(cells.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    if let Some(width) = width {
+        let others = widths[0] + widths[2] + widths[3] + 3 * GAP.len();
+        widths[1] = widths[1].min(width.saturating_sub(others).max(MIN_ABOUT_WIDTH));
+    }
+
+    let mut out = String::new();
+    let no_highlight = |_: Column, cell: &str| cell.to_owned();
+    out.push_str(&render_row(
+        &HEADERS.map(|header| header.to_owned()),
+        &widths,
+        &no_highlight,
+    ));
+    let mut rows = rows.into_iter();
+    for (category, generators) in categories {
+        if let Some(category) = category {
+            out.push_str(&format!("{}:\n", category));
+        }
+        for _ in 0..generators.len() {
+            let (generator, cells) = rows.next().expect("a row per generator");
+            out.push_str(&render_row(&cells, &widths, highlight));
+            if long {
+                out.push_str(&render_args(generator.args(), &[]));
+            }
+        }
+    }
+    out
+}
+
+/// Renders the generators grouped by the package that provides them, with the way the package is
+/// reached from the root crate.
+pub fn tree(generators: &[&Generator]) -> String {
+    let mut packages: Vec<(&PackageInfo, &Path, Vec<&Generator>)> = Vec::new();
+    for generator in generators {
+        let position = packages
+            .iter()
+            .position(|&(_, manifest_path, _)| manifest_path == generator.manifest_path());
+        match position {
+            Some(position) => packages[position].2.push(generator),
+            None => packages.push((
+                generator.package(),
+                generator.manifest_path(),
+                vec![generator],
+            )),
+        }
+    }
+    let mut out = String::new();
+    for (package, manifest_path, generators) in packages {
+        out.push_str(format!("{} {}", package.name, package.version).trim_end());
+        out.push('\n');
+        if package.dependency_path.is_empty() {
+            let dir = manifest_path.parent().unwrap_or(manifest_path);
+            out.push_str(&format!("    not a dependency, from {}\n", dir.display()));
+        } else {
+            out.push_str(&format!("    {}\n", package.dependency_path.join(" -> ")));
+        }
+        let width = generators
+            .iter()
+            .map(|generator| generator.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (i, generator) in generators.iter().enumerate() {
+            let branch = if i + 1 == generators.len() {
+                "\u{2514}\u{2500}\u{2500}"
+            } else {
+                "\u{251c}\u{2500}\u{2500}"
+            };
+            let about = row(generator, "")[1].clone();
+            let line = format!("    {} {}{}{}", branch, pad(&generator.name, width), GAP, about);
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Renders the generators and the discovery errors as JSON, one object per line. Each object has
+/// a `reason` of either `generator` or `error`, like the messages of `cargo --message-format json`.
+pub fn json_lines(generators: &[&Generator], errors: &[Error]) -> String {
+    let mut out = String::new();
+    for generator in generators {
+        out.push_str(&generator_json(generator).to_string());
+        out.push('\n');
+    }
+    for error in errors {
+        out.push_str(&json!({ "reason": "error", "message": error.to_string() }).to_string());
+        out.push('\n');
+    }
+    out
+}
+
+fn generator_json(generator: &Generator) -> Value {
+    json!({
+        "reason": "generator",
+        "id": generator.name,
+        "aliases": generator.aliases(),
+        "about": generator.about(),
+        "version": generator.version(),
+        "category": generator.category(),
+        "tags": generator.tags(),
+        "hidden": generator.is_hidden(),
+        "deprecated": generator.deprecated(),
+        "missing_features": generator.missing_features(),
+        "chain": generator.chain(),
+        "examples": generator.examples(),
+        "notes": generator.notes(),
+        "package_id": generator.package_id(),
+        "dependency_path": generator.package().dependency_path,
+        "manifest_path": generator.manifest_path().to_string_lossy(),
+        "args": generator.args().iter().map(arg_json).collect::<Vec<_>>(),
+    })
+}
+
+fn arg_json(arg: &GeneratorArg) -> Value {
+    json!({
+        "name": arg.name,
+        "help": arg.help,
+        "long": arg.long,
+        "short": arg.short,
+        "value_name": arg.value_name,
+        "default_value": arg.default_value,
+        "required": arg.required,
+        "multiple": arg.multiple,
+        "index": arg.index,
+        "possible_values": arg.possible_values,
+    })
+}
+
+/// Everything known about the generator for `cargo gen info`. The `defaults` of the project config
+/// are shown in place of the defaults of the generator.
+pub fn info(generator: &Generator, defaults: &[ArgDefault]) -> String {
+    let mut out = match generator.version() {
+        Some(version) => format!("{} {}\n", generator.name, version),
+        None => format!("{}\n", generator.name),
+    };
+    if let Some(about) = generator.about() {
+        out.push_str(&format!("{}\n", about));
+    }
+    out.push('\n');
+
+    let package = generator.package();
+    let mut fields = vec![
+        (
+            "Package",
+            format!("{} {}", package.name, package.version)
+                .trim_end()
+                .to_owned(),
+        ),
+        ("Package id", package.id.clone()),
+        ("Reached by", package.dependency_path.join(" -> ")),
+        (
+            "Manifest",
+            generator.manifest_path().to_string_lossy().into_owned(),
+        ),
+        ("Factory", generator.factory().to_owned()),
+    ];
+    if !generator.aliases().is_empty() {
+        fields.push(("Aliases", generator.aliases().join(", ")));
+    }
+    if let Some(category) = generator.category() {
+        fields.push(("Category", category.to_owned()));
+    }
+    if !generator.tags().is_empty() {
+        fields.push(("Tags", generator.tags().join(", ")));
+    }
+    if !generator.chain().is_empty() {
+        fields.push(("Chain", generator.chain().join(" -> ")));
+    }
+    let notes = notes(generator);
+    if !notes.is_empty() {
+        fields.push(("Status", notes.join("; ")));
+    }
+    let width = fields
+        .iter()
+        .map(|&(label, _)| label.len() + 1)
+        .max()
+        .unwrap_or(0);
+    for (label, value) in fields {
+        out.push_str(format!("{}{}{}", pad(&format!("{}:", label), width), GAP, value).trim_end());
+        out.push('\n');
+    }
+
+    if !generator.args().is_empty() {
+        out.push_str("\nArguments:\n");
+        out.push_str(&render_args(generator.args(), defaults));
+    }
+    if !generator.examples().is_empty() {
+        out.push_str("\nExamples:\n");
+        for example in generator.examples() {
+            out.push_str(&format!("    {}\n", example));
+        }
+    }
+    if let Some(notes) = generator.notes() {
+        out.push_str("\nNotes:\n");
+        for line in notes.lines() {
+            out.push_str(format!("    {}", line).trim_end());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// The name of the generator with notes on its state, if any.
+pub fn describe(generator: &Generator) -> String {
+    let notes = notes(generator);
+    if notes.is_empty() {
+        generator.name.clone()
+    } else {
+        format!("{} ({})", generator.name, notes.join("; "))
+    }
+}
+
+fn notes(generator: &Generator) -> Vec<String> {
+    let mut notes = Vec::new();
+    if let Some(replacement) = generator.deprecated() {
+        notes.push(format!("deprecated: {}", replacement));
+    }
+    if generator.is_hidden() {
+        notes.push("hidden".to_owned());
+    }
+    if !generator.is_available() {
+        notes.push(format!(
+            "unavailable: requires {}",
+            generator.missing_features().join(", ")
+        ));
+    }
+    notes
+}
+
+fn row(generator: &Generator, indent: &str) -> [String; 4] {
+    let mut about = generator.about().unwrap_or("").to_owned();
+    let notes = notes(generator);
+    if !notes.is_empty() {
+        if !about.is_empty() {
+            about.push(' ');
+        }
+        about.push_str(&format!("({})", notes.join("; ")));
+    }
+    let package = generator.package();
+    [
+        format!("{}{}", indent, generator.name),
+        about,
+        generator.version().unwrap_or("").to_owned(),
+        format!("{} {}", package.name, package.version)
+            .trim_end()
+            .to_owned(),
+    ]
+}
+
+fn render_row(
+    cells: &[String; 4],
+    widths: &[usize; 4],
+    highlight: &dyn Fn(Column, &str) -> String,
+) -> String {
+    // Highlight after padding, escape codes take no room on the terminal.
+    let line = cells
+        .iter()
+        .zip(widths.iter())
+        .enumerate()
+        .map(|(column, (cell, width))| {
+            let cell = pad(&truncate(cell, *width), *width);
+            match column {
+                0 => highlight(Column::Id, &cell),
+                1 => highlight(Column::About, &cell),
+                _ => cell,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(GAP);
+    format!("{}\n", line.trim_end())
+}
+
+/// One line per argument, e.g. `--crate-root <FOLDER>  The root folder [default: .]`.
+fn render_args(args: &[GeneratorArg], defaults: &[ArgDefault]) -> String {
+    let usages = args.iter().map(usage).collect::<Vec<_>>();
+    let width = usages
+        .iter()
+        .map(|usage| usage.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for (arg, usage) in args.iter().zip(usages.iter()) {
+        let help = match defaults.iter().find(|default| default.name == arg.name) {
+            None => arg_help(arg),
+            Some(default) => {
+                let arg = GeneratorArg {
+                    default_value: None,
+                    ..arg.clone()
+                };
+                let value = if default.values.is_empty() {
+                    "set".to_owned()
+                } else {
+                    default.values.join(" ")
+                };
+                format!("{} [project default: {}]", arg_help(&arg), value)
+                    .trim_start()
+                    .to_owned()
+            }
+        };
+        let line = format!("    {}{}{}", pad(usage, width), GAP, help);
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// The help of the argument followed by its settings worth knowing.
+pub(crate) fn arg_help(arg: &GeneratorArg) -> String {
+    let mut help = arg.help.clone().unwrap_or_default();
+    if arg.required {
+        help.push_str(" (required)");
+    }
+    if let Some(ref default_value) = arg.default_value {
+        help.push_str(&format!(" [default: {}]", default_value));
+    }
+    if !arg.possible_values.is_empty() {
+        help.push_str(&format!(" [possible values: {}]", arg.possible_values.join(", ")));
+    }
+    help.trim_start().to_owned()
+}
+
+/// How the argument is written on the command line, e.g. `-d, --db <DB>` or `<NAME>`.
+pub(crate) fn usage(arg: &GeneratorArg) -> String {
+    let value_name = arg.value_name.as_ref().unwrap_or(&arg.name);
+    let multiple = if arg.multiple { "..." } else { "" };
+    if arg.is_positional() {
+        return format!("<{}>{}", value_name, multiple);
+    }
+    let mut usage = match (&arg.short, &arg.long) {
+        (Some(short), Some(long)) => format!("-{}, --{}", short, long),
+        (Some(short), None) => format!("-{}", short),
+        (None, Some(long)) => format!("--{}", long),
+        (None, None) => unreachable!(),
+    };
+    if arg.takes_value() {
+        usage.push_str(&format!(" <{}>", value_name));
+    }
+    usage.push_str(multiple);
+    usage
+}
+
+fn pad(cell: &str, width: usize) -> String {
+    let len = cell.chars().count();
+    format!("{}{}", cell, " ".repeat(width.saturating_sub(len)))
+}
+
+fn truncate(cell: &str, width: usize) -> String {
+    if cell.chars().count() <= width {
+        return cell.to_owned();
+    }
+    let mut truncated = cell.chars().take(width.saturating_sub(3)).collect::<String>();
+    truncated.push_str("...");
+    truncated
+}
+
+#[cfg(test)]
+mod listing_test {
+    extern crate yaml_rust;
+
+    use self::yaml_rust::YamlLoader;
+    use failure::err_msg;
+    use serde_json;
+    use super::*;
+
+    fn generator(yaml: &str) -> Generator {
+        Generator::try_from_yaml(&YamlLoader::load_from_str(yaml).unwrap()[0]).unwrap()
+    }
+
+    #[test]
+    fn it_aligns_the_columns() {
+        let app = generator("name: a.app\nfactory: f\nabout: An app\nversion: \"0.1\"");
+        let model = generator("name: a.model\nfactory: f\nabout: A model generator");
+        assert_eq!(
+            "GENERATOR  ABOUT              VERSION  PACKAGE\n\
+             a.app      An app             0.1\n\
+             a.model    A model generator\n",
+            table(&[&app, &model], None, false)
+        );
+    }
+
+    #[test]
+    fn it_shortens_the_about_column_to_fit() {
+        let app = generator(
+            "name: a.app\nfactory: f\nabout: An application generator with a long description",
+        );
+        let out = table(&[&app], Some(60), false);
+        assert!(out.lines().all(|line| line.chars().count() <= 60));
+        assert!(out.contains("An application generator wit..."));
+    }
+
+    #[test]
+    fn it_groups_the_rows_by_category() {
+        let app = generator("name: a.app\nfactory: f\ncategory: apps");
+        let test = generator("name: a.test\nfactory: f\ncategory: testing");
+        let other = generator("name: a.other\nfactory: f");
+        assert_eq!(
+            "GENERATOR  ABOUT  VERSION  PACKAGE\na.other\napps:\n  a.app\ntesting:\n  a.test\n",
+            table(&[&test, &app, &other], None, false)
+        );
+    }
+
+    #[test]
+    fn it_lists_the_arguments_in_the_long_format() {
+        let app = generator(
+            "name: a.app\nfactory: f\nargs:\n  - NAME:\n      help: The name\n      \
+             required: true\n  - crate-root:\n      help: The root\n      long: crate-root\n      \
+             value_name: FOLDER\n      default_value: \".\"",
+        );
+        assert_eq!(
+            "GENERATOR  ABOUT  VERSION  PACKAGE\n\
+             a.app\n    \
+             <NAME>                 The name (required)\n    \
+             --crate-root <FOLDER>  The root [default: .]\n",
+            table(&[&app], None, true)
+        );
+    }
+
+    #[test]
+    fn it_prints_a_json_object_per_generator_and_error() {
+        let app = generator(
+            "name: a.app\nfactory: f\nabout: An app\nargs:\n  - NAME:\n      required: true",
+        );
+        let out = json_lines(&[&app], &[err_msg("A generator name is not present")]);
+        let lines = out
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(2, lines.len());
+        assert_eq!("generator", lines[0]["reason"]);
+        assert_eq!("a.app", lines[0]["id"]);
+        assert_eq!("An app", lines[0]["about"]);
+        assert_eq!(Value::Null, lines[0]["version"]);
+        assert_eq!("NAME", lines[0]["args"][0]["name"]);
+        assert_eq!(true, lines[0]["args"][0]["required"]);
+        assert_eq!("error", lines[1]["reason"]);
+        assert_eq!("A generator name is not present", lines[1]["message"]);
+    }
+
+    #[test]
+    fn it_describes_one_generator_in_detail() {
+        let app = generator(
+            "name: a.app\nfactory: a::App\nversion: \"0.2\"\nabout: An app\n\
+             aliases: [a.application]\nchain: [a.model, a.test]\nargs:\n  - NAME:\n      \
+             help: The name\nexamples: [cargo gen a.app blog]\nnotes: Run it once",
+        );
+        assert_eq!(
+            "a.app 0.2\n\
+             An app\n\
+             \n\
+             Package:\n\
+             Package id:\n\
+             Reached by:\n\
+             Manifest:\n\
+             Factory:     a::App\n\
+             Aliases:     a.application\n\
+             Chain:       a.model -> a.test\n\
+             \n\
+             Arguments:\n    \
+             <NAME>  The name\n\
+             \n\
+             Examples:\n    \
+             cargo gen a.app blog\n\
+             \n\
+             Notes:\n    \
+             Run it once\n",
+            info(&app, &[])
+        );
+    }
+
+    #[test]
+    fn it_shows_the_project_defaults_of_the_arguments() {
+        let app = generator(
+            "name: a.app\nfactory: f\nargs:\n  - root:\n      long: root\n      \
+             default_value: \".\"\n  - force:\n      long: force\n      help: Overwrite",
+        );
+        let defaults = [
+            ArgDefault {
+                name: "root".to_owned(),
+                values: vec!["app".to_owned()],
+            },
+            ArgDefault {
+                name: "force".to_owned(),
+                values: vec![],
+            },
+        ];
+        assert!(info(&app, &defaults).contains(
+            "Arguments:\n    \
+             --root <root>  [project default: app]\n    \
+             --force        Overwrite [project default: set]\n"
+        ));
+    }
+
+    #[test]
+    fn it_draws_a_tree_of_the_packages_and_their_generators() {
+        let mut app = generator("name: a.app\nfactory: f\nabout: An app");
+        let mut model = generator("name: a.model\nfactory: f");
+        let mut local = generator("name: b.local\nfactory: f");
+        let a = PackageInfo {
+            id: "a 0.2.0".to_owned(),
+            name: "a".to_owned(),
+            version: "0.2.0".to_owned(),
+            dependency_path: vec!["root".to_owned(), "kit".to_owned(), "a".to_owned()],
+        };
+        app.set_package(a.clone(), "/a/cargo_generators.yaml".into());
+        model.set_package(a, "/a/cargo_generators.yaml".into());
+        local.set_package(
+            PackageInfo {
+                name: "b".to_owned(),
+                ..PackageInfo::default()
+            },
+            "/gens/b/cargo_generators.yaml".into(),
+        );
+        assert_eq!(
+            "a 0.2.0\n    \
+             root -> kit -> a\n    \
+             \u{251c}\u{2500}\u{2500} a.app    An app\n    \
+             \u{2514}\u{2500}\u{2500} a.model\n\
+             b\n    \
+             not a dependency, from /gens/b\n    \
+             \u{2514}\u{2500}\u{2500} b.local\n",
+            tree(&[&app, &model, &local])
+        );
+    }
+
+    #[test]
+    fn it_notes_the_state_of_the_generator() {
+        let old = generator("name: a.old\nfactory: f\nhidden: true\ndeprecated: use a.new");
+        assert_eq!("a.old (deprecated: use a.new; hidden)", describe(&old));
+    }
+}
@@ -0,0 +1,424 @@
+//! Package roots that are searched for generators although they are not in the dependency graph.
+//!
+//! They come from the `generator-paths` and the `generator-packs` (see the `git` module) of
+//! `[workspace.metadata.cargo-gen]` in the workspace manifest, from the `generator-paths` of the
+//! cargo gen config (see the `config` module) and from the user-level generators directory
+//! (`~/.cargo-gen/generators`). They are searched after the dependencies, in that order.
+extern crate dirs;
+extern crate toml;
+
+use std::env;
+use std::fs::{self, create_dir_all, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use failure::{err_msg, Error};
+use config::Config;
+use gen::PackageInfo;
+use git::{GitPack, PackLock};
+
+/// The cargo-gen home directory. `CARGO_GEN_HOME` overrides the default `~/.cargo-gen`.
+pub fn cargo_gen_home() -> Result<PathBuf, Error> {
+    match env::var_os("CARGO_GEN_HOME") {
+        Some(home) => Ok(PathBuf::from(home)),
+        None => dirs::home_dir()
+            .map(|home| home.join(".cargo-gen"))
+            .ok_or_else(|| err_msg("Could not determine the home directory")),
+    }
+}
+
+/// Points `CARGO_GEN_HOME` and `CARGO_GEN_CONFIG` to an empty directory for the rest of the test
+/// run, so that discovery does not find the generators and the config of the machine. The tests
+/// share the directory, it is created once.
+#[cfg(test)]
+pub(crate) fn isolate_home() {
+    use std::sync::Once;
+    use tempdir::TempDir;
+
+    static ISOLATE: Once = Once::new();
+    ISOLATE.call_once(|| {
+        let home = TempDir::new("cargo-gen-home").unwrap().into_path();
+        env::set_var("CARGO_GEN_HOME", &home);
+        env::set_var("CARGO_GEN_CONFIG", home.join("config.toml"));
+    });
+}
+
+/// All the extra package roots of the workspace at `workspace_root`, with the `generator-paths`
+/// of `config` and the `pack_roots` from `generator_pack_roots`.
+pub fn extra_package_roots(
+    workspace_root: &Path,
+    config: &Config,
+    pack_roots: Vec<Result<PathBuf, Error>>,
+) -> Vec<Result<PathBuf, Error>> {
+    let mut roots = workspace_generator_paths(workspace_root);
+    roots.extend(config.generator_paths().into_iter().map(|path| canonical_dir(&path)));
+    roots.extend(pack_roots);
+    match cargo_gen_home() {
+        Ok(home) => roots.extend(user_generator_roots(&home)),
+        Err(e) => roots.push(Err(e)),
+    }
+    roots
+}
+
+/// Checks out the `generator-packs` of the workspace manifest, at the commits of the lockfile if
+/// they are locked. With `update` the packs that follow a branch move to its latest commit. This
+/// may fetch the packs and write the lockfile, discovery only does it when it rebuilds the index.
+pub fn generator_pack_roots(workspace_root: &Path, update: bool) -> Vec<Result<PathBuf, Error>> {
+    match cargo_gen_home() {
+        Ok(home) => workspace_generator_packs(workspace_root, &home, update),
+        Err(e) => vec![Err(e)],
+    }
+}
+
+/// Registers the generator crate at `crate_path` in the user-level generators directory so that
+/// it is available in every project.
+pub fn install<P: AsRef<Path>>(crate_path: P) -> Result<PathBuf, Error> {
+    install_into(&cargo_gen_home()?, crate_path.as_ref())
+}
+
+/// The `generator-paths` of the workspace manifest, relative to the workspace root.
+fn workspace_generator_paths(workspace_root: &Path) -> Vec<Result<PathBuf, Error>> {
+    let paths = match workspace_setting(workspace_root, "generator-paths") {
+        Ok(Some(paths)) => paths,
+        Ok(None) => return vec![],
+        Err(e) => return vec![Err(e)],
+    };
+    match paths.as_array() {
+        None => vec![Err(err_msg(
+            "workspace.metadata.cargo-gen.generator-paths is not an array",
+        ))],
+        Some(paths) => paths
+            .iter()
+            .map(|path| {
+                path.as_str()
+                    .ok_or_else(|| err_msg("A generator path is not a string"))
+                    .and_then(|path| canonical_dir(&workspace_root.join(path)))
+            })
+            .collect(),
+    }
+}
+
+/// Checks out the `generator-packs` of the workspace manifest and records their commits.
+fn workspace_generator_packs(
+    workspace_root: &Path,
+    home: &Path,
+    update: bool,
+) -> Vec<Result<PathBuf, Error>> {
+    let packs = match workspace_setting(workspace_root, "generator-packs") {
+        Ok(Some(packs)) => packs,
+        Ok(None) => return vec![],
+        Err(e) => return vec![Err(e)],
+    };
+    let packs = match packs.as_array() {
+        Some(packs) => packs
+            .iter()
+            .map(GitPack::try_from_toml)
+            .collect::<Vec<_>>(),
+        None => {
+            return vec![Err(err_msg(
+                "workspace.metadata.cargo-gen.generator-packs is not an array",
+            ))]
+        }
+    };
+    let mut lock = match PackLock::load(workspace_root) {
+        Ok(lock) => lock,
+        Err(e) => return vec![Err(e)],
+    };
+
+    let mut roots = Vec::new();
+    let mut valid_packs = Vec::new();
+    for pack in packs {
+        match pack {
+            Err(e) => roots.push(Err(e)),
+            Ok(pack) => {
+                let locked_commit = match lock.commit_of(&pack) {
+                    Some(_) if update && pack.follows_a_branch() => None,
+                    commit => commit.map(|commit| commit.to_owned()),
+                };
+                roots.push(
+                    pack.checkout(home, locked_commit.as_deref())
+                        .and_then(|(root, commit)| {
+                            lock.record(&pack, &commit);
+                            canonical_dir(&root)
+                        }),
+                );
+                valid_packs.push(pack);
+            }
+        }
+    }
+    lock.retain(&valid_packs);
+    if let Err(e) = lock.save() {
+        roots.push(Err(e));
+    }
+    roots
+}
+
+/// A key of `[workspace.metadata.cargo-gen]` in the workspace manifest.
+fn workspace_setting(workspace_root: &Path, key: &str) -> Result<Option<toml::Value>, Error> {
+    let manifest = read_manifest(&workspace_root.join("Cargo.toml"))?;
+    Ok(manifest
+        .get("workspace")
+        .and_then(|workspace| workspace.get("metadata"))
+        .and_then(|metadata| metadata.get("cargo-gen"))
+        .and_then(|cargo_gen| cargo_gen.get(key))
+        .cloned())
+}
+
+/// Every directory (or link to one) in the `generators` directory of the cargo-gen home.
+fn user_generator_roots(home: &Path) -> Vec<Result<PathBuf, Error>> {
+    let generators_dir = home.join("generators");
+    if !generators_dir.exists() {
+        return vec![];
+    }
+    let mut entries = match fs::read_dir(&generators_dir)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+    {
+        Ok(entries) => entries,
+        Err(e) => return vec![Err(Error::from(e))],
+    };
+    entries.sort_by_key(|entry| entry.file_name());
+    entries
+        .iter()
+        .map(|entry| canonical_dir(&entry.path()))
+        .collect()
+}
+
+fn install_into(home: &Path, crate_path: &Path) -> Result<PathBuf, Error> {
+    let crate_path = canonical_dir(crate_path)?;
+    if !crate_path.join("cargo_generators.yaml").is_file() {
+        bail!(
+            "{} does not have a cargo_generators.yaml",
+            crate_path.display()
+        );
+    }
+    let manifest = read_manifest(&crate_path.join("Cargo.toml"))?;
+    let name = manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str())
+        .ok_or_else(|| format_err!("{} is not a package", crate_path.display()))?;
+
+    let generators_dir = home.join("generators");
+    create_dir_all(&generators_dir)?;
+    let link = generators_dir.join(name);
+    if let Ok(link_metadata) = fs::symlink_metadata(&link) {
+        if !link_metadata.file_type().is_symlink() {
+            bail!("{} already exists and is not a link", link.display());
+        }
+        // Registering a crate again points the link to the new location.
+        fs::remove_file(&link)?;
+    }
+    symlink_dir(&crate_path, &link)?;
+    Ok(link)
+}
+
+pub(crate) fn read_manifest(path: &Path) -> Result<toml::Value, Error> {
+    let mut toml_str = String::new();
+    File::open(path)
+        .map_err(|e| format_err!("{} could not be opened: {}", path.display(), e))?
+        .read_to_string(&mut toml_str)?;
+    toml_str
+        .parse::<toml::Value>()
+        .map_err(|e| format_err!("{} is not a valid manifest: {}", path.display(), e))
+}
+
+/// Describes a package outside the dependency graph, with an id in the format of
+/// `cargo metadata`. Directories without a valid manifest are named after the directory.
+pub(crate) fn local_package(dir: &Path) -> PackageInfo {
+    let package = read_manifest(&dir.join("Cargo.toml"))
+        .ok()
+        .and_then(|manifest| manifest.get("package").cloned());
+    let field = |key: &str| {
+        package
+            .as_ref()
+            .and_then(|package| package.get(key))
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_owned())
+    };
+    let source = format!("(path+file://{})", dir.display());
+    match (field("name"), field("version")) {
+        (Some(name), Some(version)) => PackageInfo {
+            id: format!("{} {} {}", name, version, source),
+            name,
+            version,
+            dependency_path: vec![],
+        },
+        _ => PackageInfo {
+            id: source,
+            name: dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            version: String::new(),
+            dependency_path: vec![],
+        },
+    }
+}
+
+fn canonical_dir(path: &Path) -> Result<PathBuf, Error> {
+    match path.canonicalize() {
+        Ok(ref dir) if dir.is_dir() => Ok(dir.to_path_buf()),
+        _ => bail!("Generator path {} is not a directory", path.display()),
+    }
+}
+
+#[cfg(unix)]
+fn symlink_dir(src: &Path, dst: &Path) -> io::Result<()> {
+    ::std::os::unix::fs::symlink(src, dst)
+}
+
+#[cfg(windows)]
+fn symlink_dir(src: &Path, dst: &Path) -> io::Result<()> {
+    ::std::os::windows::fs::symlink_dir(src, dst)
+}
+
+#[cfg(test)]
+mod roots_test {
+    extern crate cargo_gen_helpers;
+
+    use self::cargo_gen_helpers::test_helpers::create_empty_crate;
+    use self::cargo_gen_helpers::{create_file, modify_file};
+    use super::*;
+
+    fn add_generator_paths(crate_dir: &Path, paths: &str) {
+        modify_file(crate_dir.join("Cargo.toml"), |contents| {
+            Ok(Some(format!(
+                "{}\n[workspace.metadata.cargo-gen]\ngenerator-paths = {}\n",
+                contents, paths
+            )))
+        }).unwrap();
+    }
+
+    #[test]
+    fn it_reads_generator_paths_relative_to_the_workspace_root() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        create_file(crate_dir.path().join("gens/a/Cargo.toml"), "").unwrap();
+        add_generator_paths(crate_dir.path(), "[\"gens/a\"]");
+        assert_eq!(
+            vec![crate_dir.path().join("gens/a").canonicalize().unwrap()],
+            workspace_generator_paths(crate_dir.path())
+                .into_iter()
+                .map(|res| res.unwrap())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn it_describes_a_local_package_from_its_manifest() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        assert_eq!(
+            PackageInfo {
+                id: format!("cargo-gen-test 0.1.0 (path+file://{})", crate_dir.path().display()),
+                name: "cargo-gen-test".to_owned(),
+                version: "0.1.0".to_owned(),
+                dependency_path: vec![],
+            },
+            local_package(crate_dir.path())
+        );
+        let dir = crate_dir.path().join("gens");
+        let package = local_package(&dir);
+        assert_eq!(format!("(path+file://{})", dir.display()), package.id);
+        assert_eq!("gens", package.name);
+    }
+
+    #[test]
+    fn it_has_no_generator_paths_by_default() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        assert_eq!(0, workspace_generator_paths(crate_dir.path()).len());
+    }
+
+    #[test]
+    fn it_reports_generator_paths_that_do_not_exist() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        add_generator_paths(crate_dir.path(), "[\"missing\"]");
+        let roots = workspace_generator_paths(crate_dir.path());
+        assert_eq!(1, roots.len());
+        assert!(roots[0].is_err());
+    }
+
+    #[test]
+    fn it_reports_generator_paths_that_are_not_an_array() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        add_generator_paths(crate_dir.path(), "\"gens\"");
+        let roots = workspace_generator_paths(crate_dir.path());
+        assert_eq!(1, roots.len());
+        assert!(roots[0].is_err());
+    }
+
+    #[test]
+    fn it_checks_out_generator_packs_and_locks_their_commits() {
+        let home = create_empty_crate("cargo-gen-home").unwrap();
+        let repo = create_empty_crate("cargo-gen-pack").unwrap();
+        let git = |args: &[&str]| {
+            assert!(
+                ::std::process::Command::new("git")
+                    .arg("-C")
+                    .arg(repo.path())
+                    .args(["-c", "user.name=cargo-gen", "-c", "user.email=cargo-gen@example.com"])
+                    .args(args)
+                    .status()
+                    .unwrap()
+                    .success()
+            )
+        };
+        git(&["init", "--quiet"]);
+        git(&["add", "--all"]);
+        git(&["commit", "--quiet", "-m", "first"]);
+        git(&["tag", "v1"]);
+
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        modify_file(crate_dir.path().join("Cargo.toml"), |contents| {
+            Ok(Some(format!(
+                "{}\n[workspace.metadata.cargo-gen]\n\
+                 generator-packs = [{{ git = \"file://{}\", tag = \"v1\" }}]\n",
+                contents,
+                repo.path().display()
+            )))
+        }).unwrap();
+
+        let roots = workspace_generator_packs(crate_dir.path(), home.path(), false);
+        assert_eq!(1, roots.len());
+        assert!(roots[0].as_ref().unwrap().join("Cargo.toml").is_file());
+        let lock = PackLock::load(crate_dir.path()).unwrap();
+        let pack = GitPack {
+            url: format!("file://{}", repo.path().display()),
+            reference: ::git::GitReference::Tag("v1".to_owned()),
+            path: None,
+        };
+        assert!(lock.commit_of(&pack).is_some());
+    }
+
+    #[test]
+    fn it_has_no_user_generators_without_a_generators_directory() {
+        let home = create_empty_crate("cargo-gen-home").unwrap();
+        assert_eq!(0, user_generator_roots(home.path()).len());
+    }
+
+    #[test]
+    fn it_installs_a_generator_crate_into_the_user_generators() {
+        let home = create_empty_crate("cargo-gen-home").unwrap();
+        let gen_crate = create_empty_crate("cargo-gen-shared").unwrap();
+        create_file(
+            gen_crate.path().join("cargo_generators.yaml"),
+            "- name: shared.gen\n  factory: f",
+        ).unwrap();
+
+        let link = install_into(home.path(), gen_crate.path()).unwrap();
+        assert_eq!(home.path().join("generators/cargo-gen-shared"), link);
+        assert_eq!(
+            vec![gen_crate.path().canonicalize().unwrap()],
+            user_generator_roots(home.path())
+                .into_iter()
+                .map(|res| res.unwrap())
+                .collect::<Vec<_>>()
+        );
+        // Installing again is fine.
+        install_into(home.path(), gen_crate.path()).unwrap();
+    }
+
+    #[test]
+    fn it_refuses_to_install_a_crate_without_generators() {
+        let home = create_empty_crate("cargo-gen-home").unwrap();
+        let gen_crate = create_empty_crate("cargo-gen-shared").unwrap();
+        assert!(install_into(home.path(), gen_crate.path()).is_err());
+    }
+}
@@ -0,0 +1,371 @@
+//! Runs a generator.
+//!
+//! Generators are Rust code in their packages so they have to be compiled before they can run. The
+//! runner is a tiny binary crate in `cargo-gen/runners` of the target directory that depends on
+//! the package of the generator. It builds the generator with its `factory`, the path to a type
+//! that implements `From<ArgsOs>` and `CargoGenerator`, and calls `gen` on it.
+extern crate toml;
+
+use std::env;
+use std::fs::{self, create_dir_all, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use failure::{err_msg, Error};
+use config::Config;
+use gen::Generator;
+use hash::stable_hash;
+use index::{find_lockfile, target_dir};
+use roots::read_manifest;
+
+const HELPERS_PACKAGE: &str = "cargo-gen-helpers";
+/// The first cargo-gen-helpers that writes to the staging directory of a dry run, the ones before
+/// write to the project.
+const DRY_RUN_HELPERS_VERSION: &str = "0.0.2";
+/// The template variables are passed to the generator as `CARGO_GEN_VAR_<NAME>`.
+const VARIABLE_ENV_PREFIX: &str = "CARGO_GEN_VAR_";
+/// The conflict policy of the config, see `cargo_gen_helpers::conflict_policy`.
+const CONFLICT_ENV: &str = "CARGO_GEN_CONFLICT";
+/// The staging directory of a dry run, see `cargo_gen_helpers::is_dry_run`.
+const DRY_RUN_ENV: &str = "CARGO_GEN_DRY_RUN";
+/// Where the helpers back the files up before they write them, see the `journal` module.
+const BACKUP_ENV: &str = "CARGO_GEN_BACKUP";
+
+/// Where the generator writes the files.
+#[derive(Clone, Copy, Debug)]
+pub enum Output<'a> {
+    /// To the project. The helpers back the files up to the directory first, if there is one.
+    Project(Option<&'a Path>),
+    /// To the staging directory of a dry run instead of the project.
+    Staging(&'a Path),
+}
+
+/// Builds and runs the runner of `generator` with the generator arguments `args`, and the template
+/// variables and the conflict policy of `config`. The generator runs in the current directory, and
+/// writes to `output`.
+pub fn run(
+    generator: &Generator,
+    root_crate_path: &Path,
+    args: &[String],
+    config: &Config,
+    output: Output,
+) -> Result<ExitStatus, Error> {
+    let package_dir = generator
+        .manifest_path()
+        .parent()
+        .ok_or_else(|| format_err!("Generator {} does not belong to a package", generator.name))?;
+    let runner_dir = runner_dir(root_crate_path, package_dir)?;
+    let (manifest, main) = render_runner(package_dir, generator.factory())?;
+    write_if_changed(&runner_dir.join("Cargo.toml"), &manifest)?;
+    write_if_changed(&runner_dir.join("src").join("main.rs"), &main)?;
+    // Reuse the versions the project already resolved instead of resolving everything again.
+    let runner_lockfile = runner_dir.join("Cargo.lock");
+    if let (false, Some(lockfile)) = (runner_lockfile.exists(), find_lockfile(root_crate_path)) {
+        fs::copy(lockfile, runner_lockfile)?;
+    }
+
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    if let Output::Staging(_) = output {
+        // Build first to know which helpers the generator resolved before it can write anything.
+        let built = Command::new(&cargo)
+            .args(["build", "--quiet", "--manifest-path"])
+            .arg(runner_dir.join("Cargo.toml"))
+            .status()
+            .map_err(|e| format_err!("Could not build the generator {}: {}", generator.name, e))?;
+        if !built.success() {
+            return Ok(built);
+        }
+        check_dry_run_support(&runner_dir.join("Cargo.lock"), &generator.name)?;
+    }
+    let mut command = Command::new(cargo);
+    command
+        .args(["run", "--quiet", "--manifest-path"])
+        .arg(runner_dir.join("Cargo.toml"))
+        .args(["--", "gen", &generator.name])
+        .args(args)
+        .envs(config.variables().iter().map(|(name, value)| (variable_env(name), value)));
+    if let Some(conflict) = config.conflict() {
+        command.env(CONFLICT_ENV, conflict);
+    }
+    match output {
+        Output::Project(Some(backup_dir)) => {
+            command.env(BACKUP_ENV, backup_dir);
+        }
+        Output::Project(None) => {}
+        Output::Staging(staging_dir) => {
+            command.env(DRY_RUN_ENV, staging_dir);
+        }
+    }
+    command
+        .status()
+        .map_err(|e| format_err!("Could not run the generator {}: {}", generator.name, e))
+}
+
+/// E.g. `CARGO_GEN_VAR_CRATE_AUTHOR` for `crate-author`.
+fn variable_env(name: &str) -> String {
+    format!("{}{}", VARIABLE_ENV_PREFIX, name.to_uppercase().replace('-', "_"))
+}
+
+/// One runner per generator package so that switching between generators does not rebuild.
+fn runner_dir(root_crate_path: &Path, package_dir: &Path) -> Result<PathBuf, Error> {
+    let target_dir = target_dir(root_crate_path)
+        .ok_or_else(|| err_msg("Could not find the target directory for the generator runner"))?;
+    let name = package_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let hash = stable_hash(package_dir.to_string_lossy().as_bytes());
+    Ok(target_dir
+        .join("cargo-gen")
+        .join("runners")
+        .join(format!("{}-{}", name, hash)))
+}
+
+/// Renders the `Cargo.toml` and the `src/main.rs` of the runner.
+fn render_runner(package_dir: &Path, factory: &str) -> Result<(String, String), Error> {
+    let package_manifest = read_manifest(&package_dir.join("Cargo.toml"))?;
+    let package_name = package_manifest
+        .get("package")
+        .and_then(|package| package.get("name"))
+        .and_then(|name| name.as_str())
+        .ok_or_else(|| format_err!("{} is not a package", package_dir.display()))?;
+    let crate_name = package_manifest
+        .get("lib")
+        .and_then(|lib| lib.get("name"))
+        .and_then(|name| name.as_str())
+        .map(|name| name.to_owned())
+        .unwrap_or_else(|| package_name.replace('-', "_"));
+
+    let mut package_dep = toml::value::Table::new();
+    package_dep.insert(
+        "path".to_owned(),
+        toml::Value::String(package_dir.to_string_lossy().into_owned()),
+    );
+    let mut dependencies = toml::value::Table::new();
+    dependencies.insert(package_name.to_owned(), toml::Value::Table(package_dep));
+    // The runner needs the very same cargo-gen-helpers as the generator for the trait to match.
+    if package_name != HELPERS_PACKAGE {
+        dependencies.insert(
+            HELPERS_PACKAGE.to_owned(),
+            helpers_dependency(&package_manifest, package_dir)?,
+        );
+    }
+
+    let mut package = toml::value::Table::new();
+    package.insert(
+        "name".to_owned(),
+        toml::Value::String("cargo-gen-runner".to_owned()),
+    );
+    package.insert("version".to_owned(), toml::Value::String("0.0.0".to_owned()));
+    package.insert("publish".to_owned(), toml::Value::Boolean(false));
+    let mut manifest = toml::value::Table::new();
+    manifest.insert("package".to_owned(), toml::Value::Table(package));
+    manifest.insert("dependencies".to_owned(), toml::Value::Table(dependencies));
+    // The runner lives in the target directory, inside the project. An empty workspace keeps it
+    // out of the project's workspace.
+    manifest.insert(
+        "workspace".to_owned(),
+        toml::Value::Table(toml::value::Table::new()),
+    );
+
+    let mut main = String::new();
+    if crate_name != "cargo_gen_helpers" {
+        main.push_str("extern crate cargo_gen_helpers;\n");
+    }
+    main.push_str(&format!(
+        "extern crate {};\n\n\
+         use cargo_gen_helpers::CargoGenerator;\n\
+         use std::env::args_os;\n\
+         use std::process::exit;\n\n\
+         fn main() {{\n    \
+         if let Err(err) = {}::from(args_os()).gen() {{\n        \
+         eprintln!(\"{{}}\", err);\n        \
+         exit(1);\n    \
+         }}\n\
+         }}\n",
+        crate_name,
+        factory_path(factory)
+    ));
+    Ok((toml::Value::Table(manifest).to_string(), main))
+}
+
+/// The cargo-gen-helpers dependency of the generator package with paths made absolute.
+fn helpers_dependency(
+    package_manifest: &toml::Value,
+    package_dir: &Path,
+) -> Result<toml::Value, Error> {
+    let dependency = package_manifest
+        .get("dependencies")
+        .and_then(|dependencies| dependencies.get(HELPERS_PACKAGE))
+        .ok_or_else(|| {
+            format_err!(
+                "The package at {} does not depend on {}",
+                package_dir.display(),
+                HELPERS_PACKAGE
+            )
+        })?;
+    let mut dependency = dependency.clone();
+    if let Some(table) = dependency.as_table_mut() {
+        let absolute_path = table
+            .get("path")
+            .and_then(|path| path.as_str())
+            .map(|path| package_dir.join(path).to_string_lossy().into_owned());
+        if let Some(path) = absolute_path {
+            table.insert("path".to_owned(), toml::Value::String(path));
+        }
+    }
+    Ok(dependency)
+}
+
+/// Fails unless every cargo-gen-helpers in `lockfile` can run dry.
+fn check_dry_run_support(lockfile: &Path, generator: &str) -> Result<(), Error> {
+    let mut content = String::new();
+    File::open(lockfile)
+        .map_err(|e| format_err!("{} could not be opened: {}", lockfile.display(), e))?
+        .read_to_string(&mut content)?;
+    let lockfile = content
+        .parse::<toml::Value>()
+        .map_err(|e| format_err!("{} is not a valid lockfile: {}", lockfile.display(), e))?;
+    let packages = lockfile
+        .get("package")
+        .and_then(|packages| packages.as_array())
+        .map(|packages| packages.as_slice())
+        .unwrap_or(&[]);
+    for package in packages {
+        if package.get("name").and_then(|name| name.as_str()) != Some(HELPERS_PACKAGE) {
+            continue;
+        }
+        let version = package
+            .get("version")
+            .and_then(|version| version.as_str())
+            .unwrap_or("");
+        if version_numbers(version) < version_numbers(DRY_RUN_HELPERS_VERSION) {
+            bail!(
+                "Generator {} cannot run dry, it uses {} {} and dry runs need {} or later",
+                generator,
+                HELPERS_PACKAGE,
+                version,
+                DRY_RUN_HELPERS_VERSION
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The numbers of `version` to compare it by, without its pre-release and build metadata.
+fn version_numbers(version: &str) -> Vec<u64> {
+    version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or("")
+        .split('.')
+        .map(|number| number.parse().unwrap_or(0))
+        .collect()
+}
+
+/// Factories may name the crate by its package name, e.g. `cargo-gen-helpers::gen::Gen`.
+fn factory_path(factory: &str) -> String {
+    match factory.find("::") {
+        Some(index) => format!("{}{}", factory[..index].replace('-', "_"), &factory[index..]),
+        None => factory.to_owned(),
+    }
+}
+
+/// Leaves unchanged files alone so that cargo does not rebuild the runner.
+fn write_if_changed(path: &Path, content: &str) -> Result<(), Error> {
+    let mut current = String::new();
+    if File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut current))
+        .is_ok() && current == content
+    {
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+    File::create(path)?.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod runner_test {
+    extern crate cargo_gen_helpers;
+
+    use self::cargo_gen_helpers::test_helpers::create_empty_crate;
+    use self::cargo_gen_helpers::{create_file, modify_file};
+    use super::*;
+
+    fn add_helpers_dependency(crate_dir: &Path, dependency: &str) {
+        modify_file(crate_dir.join("Cargo.toml"), |contents| {
+            let deps_str = "[dependencies]\n";
+            Ok(Some(contents.replace(
+                deps_str,
+                &format!("{}cargo-gen-helpers = {}\n", deps_str, dependency),
+            )))
+        }).unwrap();
+    }
+
+    #[test]
+    fn it_builds_the_generator_with_its_factory() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        add_helpers_dependency(crate_dir.path(), "\"0.0.1\"");
+        let (_, main) = render_runner(crate_dir.path(), "cargo-gen-test::gens::AppGenerator")
+            .unwrap();
+        assert!(main.contains("extern crate cargo_gen_test;"));
+        assert!(main.contains("cargo_gen_test::gens::AppGenerator::from(args_os()).gen()"));
+    }
+
+    #[test]
+    fn it_depends_on_the_generator_package_and_its_helpers() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        add_helpers_dependency(crate_dir.path(), "{ path = \"../helpers\" }");
+        let (manifest, _) = render_runner(crate_dir.path(), "cargo_gen_test::Gen").unwrap();
+        let manifest = manifest.parse::<toml::Value>().unwrap();
+        assert_eq!(
+            Some(crate_dir.path().to_str().unwrap()),
+            manifest["dependencies"]["cargo-gen-test"]["path"].as_str()
+        );
+        assert_eq!(
+            Some(crate_dir.path().join("../helpers").to_str().unwrap()),
+            manifest["dependencies"]["cargo-gen-helpers"]["path"].as_str()
+        );
+    }
+
+    #[test]
+    fn it_fails_if_the_generator_package_does_not_depend_on_the_helpers() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        assert!(render_runner(crate_dir.path(), "cargo_gen_test::Gen").is_err());
+    }
+
+    #[test]
+    fn it_runs_dry_only_with_helpers_that_support_it() {
+        let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+        let lockfile = crate_dir.path().join("Cargo.lock");
+        let package = |name: &str, version: &str| {
+            format!("[[package]]\nname = \"{}\"\nversion = \"{}\"\n", name, version)
+        };
+        let lock = |versions: &[&str]| {
+            let packages = versions
+                .iter()
+                .map(|version| package("cargo-gen-helpers", version))
+                .collect::<String>();
+            create_file(&lockfile, &(package("yaml-rust", "0.0.1") + &packages)).unwrap();
+        };
+
+        lock(&["0.0.2"]);
+        assert!(check_dry_run_support(&lockfile, "app").is_ok());
+        lock(&["0.1.0-beta"]);
+        assert!(check_dry_run_support(&lockfile, "app").is_ok());
+        lock(&["0.0.2", "0.0.1"]);
+        assert_eq!(
+            "Generator app cannot run dry, it uses cargo-gen-helpers 0.0.1 and dry runs need 0.0.2 \
+             or later",
+            check_dry_run_support(&lockfile, "app").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn it_names_the_variable_environment_after_the_variable() {
+        assert_eq!("CARGO_GEN_VAR_CRATE_AUTHOR", variable_env("crate-author"));
+    }
+}
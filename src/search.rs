@@ -0,0 +1,199 @@
+//! Finds generators by a search term for `cargo gen search`.
+use gen::Generator;
+
+const HIGHLIGHT_START: &str = "\x1b[1;31m";
+const HIGHLIGHT_END: &str = "\x1b[0m";
+
+/// A search term. Terms with `*` or `?` are globs on the generator id, the others match a part of
+/// the id, a word of the about text or a tag. Case is ignored.
+#[derive(Debug)]
+pub struct Pattern {
+    term: String,
+    glob: bool,
+}
+
+impl Pattern {
+    pub fn new(term: &str) -> Pattern {
+        Pattern {
+            term: term.to_lowercase(),
+            glob: term.contains('*') || term.contains('?'),
+        }
+    }
+
+    pub fn matches(&self, generator: &Generator) -> bool {
+        if self.glob {
+            return glob_match(&self.term, &generator.name.to_lowercase());
+        }
+        generator.name.to_lowercase().contains(&self.term)
+            || generator.about().map_or(false, |about| {
+                about
+                    .split(|c: char| !c.is_alphanumeric())
+                    .any(|word| word.to_lowercase() == self.term)
+            })
+            || generator
+                .tags()
+                .iter()
+                .any(|tag| tag.to_lowercase() == self.term)
+    }
+
+    /// Wraps the parts of the generator id `text` that match in terminal escape codes. Globs
+    /// highlight the whole id if it matches.
+    pub fn highlight_id(&self, text: &str) -> String {
+        if self.term.is_empty() {
+            return text.to_owned();
+        }
+        if self.glob {
+            return if glob_match(&self.term, &text.trim().to_lowercase()) {
+                format!("{}{}{}", HIGHLIGHT_START, text, HIGHLIGHT_END)
+            } else {
+                text.to_owned()
+            };
+        }
+        // Lowercasing may change the length of some characters, match on chars to stay aligned.
+        let chars = text.chars().collect::<Vec<_>>();
+        let term = self.term.chars().collect::<Vec<_>>();
+        let mut out = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let found = i + term.len() <= chars.len()
+                && chars[i..i + term.len()]
+                    .iter()
+                    .zip(term.iter())
+                    .all(|(c, t)| c.to_lowercase().eq(t.to_lowercase()));
+            if found {
+                out.push_str(HIGHLIGHT_START);
+                out.extend(&chars[i..i + term.len()]);
+                out.push_str(HIGHLIGHT_END);
+                i += term.len();
+            } else {
+                out.push(chars[i]);
+                i += 1;
+            }
+        }
+        out
+    }
+
+    /// Wraps the words of the about `text` that match in terminal escape codes. Globs only match
+    /// the id, they highlight nothing here.
+    pub fn highlight_about(&self, text: &str) -> String {
+        if self.glob || self.term.is_empty() {
+            return text.to_owned();
+        }
+        let mut out = String::new();
+        let mut word = String::new();
+        let flush = |word: &mut String, out: &mut String| {
+            if word.to_lowercase() == self.term {
+                out.push_str(HIGHLIGHT_START);
+                out.push_str(word);
+                out.push_str(HIGHLIGHT_END);
+            } else {
+                out.push_str(word);
+            }
+            word.clear();
+        };
+        for c in text.chars() {
+            if c.is_alphanumeric() {
+                word.push(c);
+            } else {
+                flush(&mut word, &mut out);
+                out.push(c);
+            }
+        }
+        flush(&mut word, &mut out);
+        out
+    }
+}
+
+/// Matches `text` against a glob where `*` is any run of characters and `?` is one character.
+fn glob_match(glob: &str, text: &str) -> bool {
+    let glob = glob.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    let (mut g, mut t) = (0, 0);
+    // Where to resume after the last `*` if the rest does not match.
+    let mut backtrack = None;
+    while t < text.len() {
+        match glob.get(g) {
+            Some('*') => {
+                backtrack = Some((g, t));
+                g += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                g += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, star_t)) => {
+                    g = star + 1;
+                    t = star_t + 1;
+                    backtrack = Some((star, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    glob[g..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod search_test {
+    extern crate yaml_rust;
+
+    use self::yaml_rust::YamlLoader;
+    use super::*;
+
+    fn generator(yaml: &str) -> Generator {
+        Generator::try_from_yaml(&YamlLoader::load_from_str(yaml).unwrap()[0]).unwrap()
+    }
+
+    #[test]
+    fn it_matches_a_part_of_the_id() {
+        let app = generator("name: web.App\nfactory: f");
+        assert!(Pattern::new("app").matches(&app));
+        assert!(Pattern::new("b.a").matches(&app));
+        assert!(!Pattern::new("model").matches(&app));
+    }
+
+    #[test]
+    fn it_matches_a_glob_on_the_id() {
+        let app = generator("name: web.app\nfactory: f");
+        assert!(Pattern::new("web.*").matches(&app));
+        assert!(Pattern::new("*.a?p").matches(&app));
+        assert!(!Pattern::new("*.model").matches(&app));
+        assert!(!Pattern::new("app*").matches(&app));
+    }
+
+    #[test]
+    fn it_matches_a_word_of_the_about_text_or_a_tag() {
+        let app = generator(
+            "name: a.app\nfactory: f\nabout: Generate an HTTP server\ntags: [Web]",
+        );
+        assert!(Pattern::new("http").matches(&app));
+        assert!(Pattern::new("web").matches(&app));
+        assert!(!Pattern::new("serv").matches(&app));
+    }
+
+    #[test]
+    fn it_highlights_the_matches() {
+        assert_eq!(
+            "web.\x1b[1;31mApp\x1b[0m \x1b[1;31mapp\x1b[0m",
+            Pattern::new("app").highlight_id("web.App app")
+        );
+        assert_eq!(
+            "\x1b[1;31mweb.app\x1b[0m",
+            Pattern::new("web.*").highlight_id("web.app")
+        );
+        assert_eq!("model", Pattern::new("web.*").highlight_id("model"));
+    }
+
+    #[test]
+    fn it_highlights_the_words_of_the_about_text_it_matches() {
+        assert_eq!(
+            "An \x1b[1;31mApp\x1b[0m for apps, \x1b[1;31mapp\x1b[0m.",
+            Pattern::new("app").highlight_about("An App for apps, app.")
+        );
+        assert_eq!(
+            "Generate a web app",
+            Pattern::new("web.*").highlight_about("Generate a web app")
+        );
+    }
+}
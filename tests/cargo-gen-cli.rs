@@ -1,13 +1,26 @@
 extern crate assert_cli;
 extern crate cargo_gen_helpers;
+extern crate tempdir;
 
-use assert_cli::Assert;
-// use cargo_gen_helpers::test_helpers::create_empty_crate;
+use assert_cli::{Assert, Environment};
+use cargo_gen_helpers::create_file;
+use cargo_gen_helpers::test_helpers::create_empty_crate;
+use tempdir::TempDir;
+
+/// The environment of `cargo gen` with an empty home and no user config, so that the generators
+/// and the config of the machine do not leak into the tests.
+fn isolated(home: &TempDir) -> Environment {
+    Environment::inherit()
+        .insert("CARGO_GEN_HOME", home.path())
+        .insert("CARGO_GEN_CONFIG", home.path().join("config.toml"))
+}
 
 #[test]
 fn it_prints_the_help_text_when_called_without_arguments() {
     // let crate_dir = create_empty_crate("gen-test").unwrap();
+    let home = TempDir::new("cargo-gen-home").unwrap();
     Assert::cargo_binary("cargo-gen")
+        .with_env(isolated(&home))
         .fails()
         .and()
         .stderr()
@@ -17,8 +30,10 @@ fn it_prints_the_help_text_when_called_without_arguments() {
 
 #[test]
 fn it_prints_the_help_text_when_called_with_the_subcommand_only() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
     Assert::cargo_binary("cargo-gen")
         .with_args(&["gen"])
+        .with_env(isolated(&home))
         .fails()
         .and()
         .stderr()
@@ -27,10 +42,31 @@ fn it_prints_the_help_text_when_called_with_the_subcommand_only() {
 }
 
 #[test]
-#[ignore]
-fn it_fails_when_both_list_and_subcommand_provided() {
+fn it_picks_a_generator_interactively_from_the_standard_input() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
     Assert::cargo_binary("cargo-gen")
-        .with_args(&["gen", "--list", "app"])
+        .with_args(&["gen", "run", "--interactive"])
+        .with_env(isolated(&home))
+        .stdin("cargo-gen.nothing\n")
+        .fails()
+        .and()
+        .stdout()
+        .contains("1) cargo-gen.generator")
+        .and()
+        .stdout()
+        .contains("No generator cargo-gen.nothing, pick one of the list")
+        .and()
+        .stderr()
+        .contains("No answer to: Generator [1-1]")
+        .unwrap();
+}
+
+#[test]
+fn it_fails_when_list_is_given_a_generator() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "list", "app"])
+        .with_env(isolated(&home))
         .fails()
         .and()
         .stderr()
@@ -38,11 +74,204 @@ fn it_fails_when_both_list_and_subcommand_provided() {
         .unwrap();
 }
 
+#[test]
+fn it_fails_when_both_the_list_flag_and_a_generator_are_given() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "--list", "app"])
+        .with_env(isolated(&home))
+        .fails()
+        .and()
+        .stderr()
+        .contains("--list cannot be used with a subcommand nor a generator")
+        .unwrap();
+}
+
+#[test]
+fn it_runs_a_generator_named_like_a_subcommand() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    let crate_dir = create_empty_crate("cargo-gen-test").unwrap();
+    create_file(
+        crate_dir.path().join("cargo_generators.yaml"),
+        "- name: list\n  factory: f\n  requires-features: [gen]",
+    ).unwrap();
+    Assert::command(&[env!("CARGO_BIN_EXE_cargo-gen"), "gen", "run", "list"])
+        .current_dir(crate_dir.path())
+        .with_env(isolated(&home))
+        .fails()
+        .and()
+        .stderr()
+        .contains("Generator list requires the cargo feature(s) gen")
+        .unwrap();
+}
+
+#[test]
+fn it_fails_to_run_without_a_generator() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "run"])
+        .with_env(isolated(&home))
+        .fails()
+        .and()
+        .stderr()
+        .contains("<GENERATOR>")
+        .unwrap();
+}
+
 #[test]
 fn it_returns_a_list_of_available_generators() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "list"])
+        .with_env(isolated(&home))
+        .stdout()
+        .is("GENERATOR            ABOUT                                                     \
+             VERSION  PACKAGE\n\
+             cargo-gen.generator  Generate a scaffold of an empty but functional generator  \
+             0.1      cargo-gen-helpers 0.0.2\n")
+        .unwrap();
+}
+
+#[test]
+fn it_traces_the_discovery_when_verbose() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "--verbose", "list"])
+        .with_env(isolated(&home))
+        .stderr()
+        .contains("Parsed generator cargo-gen.generator")
+        .unwrap();
+}
+
+#[test]
+fn it_does_not_trace_when_quiet() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "--quiet", "list"])
+        .with_env(isolated(&home).insert("CARGO_GEN_LOG", "cargo_gen::gen=debug"))
+        .stderr()
+        .doesnt_contain("Parsed generator")
+        .unwrap();
+}
+
+#[test]
+fn it_returns_a_list_of_available_generators_as_json() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "list", "--message-format", "json"])
+        .with_env(isolated(&home))
+        .stdout()
+        .contains("\"reason\":\"generator\"")
+        .and()
+        .stdout()
+        .contains("\"id\":\"cargo-gen.generator\"")
+        .unwrap();
+}
+
+#[test]
+fn it_shows_the_details_of_a_generator() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
     Assert::cargo_binary("cargo-gen")
-        .with_args(&["gen", "--list"])
+        .with_args(&["gen", "info", "cargo-gen.generator"])
+        .with_env(isolated(&home))
+        .stdout()
+        .contains("cargo_gen_helpers::gen::CargoGeneratorGenerator")
+        .and()
         .stdout()
-        .is("cargo-gen.generator\n")
+        .contains("--crate-root <FOLDER>")
+        .unwrap();
+}
+
+#[test]
+fn it_searches_the_generators() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "search", "scaffold"])
+        .with_env(isolated(&home))
+        .stdout()
+        .contains("cargo-gen.generator")
+        .unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "search", "no-such-*"])
+        .with_env(isolated(&home))
+        .fails()
+        .and()
+        .stderr()
+        .contains("No generator matches no-such-*")
+        .unwrap();
+}
+
+#[test]
+fn it_exits_with_a_distinct_status_if_discovery_fails() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    // `cargo run` would need a crate in the current directory, run the binary directly.
+    Assert::command(&[env!("CARGO_BIN_EXE_cargo-gen"), "gen", "list"])
+        .current_dir(std::env::temp_dir())
+        .with_env(isolated(&home))
+        .fails_with(1)
+        .and()
+        .stderr()
+        .contains("Could not discover the generators")
+        .unwrap();
+}
+
+#[test]
+fn it_writes_the_docs_of_the_generators() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "docs"])
+        .with_env(isolated(&home))
+        .stdout()
+        .contains("## `cargo-gen.generator`")
+        .unwrap();
+}
+
+#[test]
+fn it_shows_the_merged_config_with_the_source_of_each_value() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    let user_config = home.path().join("config.toml");
+    create_file(&user_config, "conflict = \"skip\"").unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "config", "--show"])
+        .with_env(isolated(&home))
+        .stdout()
+        .contains(format!("conflict = \"skip\"  # {}", user_config.display()).as_str())
+        .unwrap();
+}
+
+#[test]
+fn it_fails_to_replay_answers_that_were_not_recorded() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "run", "--replay", "cargo-gen.generator"])
+        .with_env(isolated(&home))
+        .fails()
+        .and()
+        .stderr()
+        .contains("No answers were recorded for cargo-gen.generator")
+        .unwrap();
+}
+
+#[test]
+fn it_validates_the_generator_manifests() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "validate"])
+        .with_env(isolated(&home))
+        .stdout()
+        .contains("generator found")
+        .unwrap();
+}
+
+#[test]
+fn it_fails_to_run_an_unknown_generator() {
+    let home = TempDir::new("cargo-gen-home").unwrap();
+    Assert::cargo_binary("cargo-gen")
+        .with_args(&["gen", "no-such.generator"])
+        .with_env(isolated(&home))
+        .fails()
+        .and()
+        .stderr()
+        .contains("Generator no-such.generator not found")
         .unwrap();
 }